@@ -0,0 +1,100 @@
+//! `#[derive(FromLox, IntoLox)]` for structs with named fields, which convert
+//! to and from Lox maps keyed by field name. Use them through the re-exports
+//! in `rslox`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+
+#[proc_macro_derive(IntoLox)]
+pub fn derive_into_lox(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match named_fields(&input) {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let keys = fields.iter().map(|field| field.to_string());
+
+    quote! {
+        impl #impl_generics ::rslox::IntoLox for #name #type_generics #where_clause {
+            fn into_lox(self) -> ::rslox::Value {
+                let mut map = ::std::collections::BTreeMap::new();
+                #(
+                    map.insert(
+                        ::std::string::String::from(#keys),
+                        ::rslox::IntoLox::into_lox(self.#fields),
+                    );
+                )*
+                ::rslox::Value::map(map)
+            }
+        }
+    }
+    .into()
+}
+
+/// Missing keys read as `nil`, so `Option` fields may be left out.
+#[proc_macro_derive(FromLox)]
+pub fn derive_from_lox(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match named_fields(&input) {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let keys: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+
+    let body: TokenStream2 = quote! {
+        let map = match value {
+            ::rslox::Value::Map(map) => ::std::cell::RefCell::borrow(map),
+            _ => return ::std::result::Result::Err(::rslox::ConversionError::new("a map")),
+        };
+
+        ::std::result::Result::Ok(Self {
+            #(
+                #fields: ::rslox::convert::field(
+                    map.get(#keys).unwrap_or(&::rslox::Value::Nil),
+                    #keys,
+                )?,
+            )*
+        })
+    };
+
+    quote! {
+        impl #impl_generics ::rslox::FromLox for #name #type_generics #where_clause {
+            fn from_lox(
+                value: &::rslox::Value,
+            ) -> ::std::result::Result<Self, ::rslox::ConversionError> {
+                #body
+            }
+        }
+    }
+    .into()
+}
+
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<Ident>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields
+                .named
+                .iter()
+                .filter_map(|field| field.ident.clone())
+                .collect()),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromLox and IntoLox can only be derived for structs with named fields",
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "FromLox and IntoLox can only be derived for structs",
+        )),
+    }
+}
@@ -1,9 +1,15 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{expr_visitor::ExprVisitor, lexer::token::Token, value::Value};
 
-#[derive(Clone)]
+use super::json::literal;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
 pub enum Expr {
     Assign(AssignExpr),
     Binary(BinaryExpr),
+    Call(CallExpr),
     Grouping(GroupingExpr),
     Literal(LiteralExpr),
     Logical(LogicalExpr),
@@ -16,6 +22,7 @@ impl Expr {
         match self {
             Expr::Assign(expr) => visitor.visit_assign_expr(expr),
             Expr::Binary(expr) => visitor.visit_binary_expr(expr),
+            Expr::Call(expr) => visitor.visit_call_expr(expr),
             Expr::Grouping(expr) => visitor.visit_grouping_expr(expr),
             Expr::Literal(expr) => visitor.visit_literal_expr(expr),
             Expr::Logical(expr) => visitor.visit_logical_expr(expr),
@@ -23,45 +30,68 @@ impl Expr {
             Expr::Variable(expr) => visitor.visit_variable_expr(expr),
         }
     }
+
+    /// The leftmost token of the expression that the AST keeps.
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Expr::Assign(expr) => &expr.name,
+            Expr::Binary(expr) => expr.left.first_token(),
+            Expr::Call(expr) => expr.callee.first_token(),
+            Expr::Grouping(expr) => expr.expression.first_token(),
+            Expr::Literal(expr) => &expr.token,
+            Expr::Logical(expr) => expr.left.first_token(),
+            Expr::Unary(expr) => &expr.operator,
+            Expr::Variable(expr) => &expr.name,
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AssignExpr {
     pub name: Token,
     pub value: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BinaryExpr {
     pub operator: Token,
     pub left: Box<Expr>,
     pub right: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CallExpr {
+    pub callee: Box<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GroupingExpr {
     pub expression: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LiteralExpr {
+    pub token: Token,
+    #[serde(with = "literal")]
     pub value: Option<Value>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LogicalExpr {
     pub operator: Token,
     pub left: Box<Expr>,
     pub right: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UnaryExpr {
     pub operator: Token,
     pub right: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct VariableExpr {
     pub name: Token,
 }
@@ -85,6 +115,16 @@ impl BinaryExpr {
     }
 }
 
+impl CallExpr {
+    pub fn new(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Self {
+        Self {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }
+    }
+}
+
 impl GroupingExpr {
     pub fn new(expression: Expr) -> Self {
         Self {
@@ -94,8 +134,11 @@ impl GroupingExpr {
 }
 
 impl LiteralExpr {
-    pub fn new(value: Value) -> Self {
-        Self { value: Some(value) }
+    pub fn new(token: Token, value: Value) -> Self {
+        Self {
+            token,
+            value: Some(value),
+        }
     }
 }
 
@@ -136,6 +179,12 @@ impl From<BinaryExpr> for Expr {
     }
 }
 
+impl From<CallExpr> for Expr {
+    fn from(expr: CallExpr) -> Self {
+        Expr::Call(expr)
+    }
+}
+
 impl From<GroupingExpr> for Expr {
     fn from(expr: GroupingExpr) -> Self {
         Expr::Grouping(expr)
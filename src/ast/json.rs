@@ -0,0 +1,169 @@
+//! JSON form of the AST, for tools that analyse or transform Lox programs
+//! outside Rust. The schema is documented in `docs/ast-json.md`.
+
+use serde::{de::Error, Deserialize, Serialize};
+
+use super::statement::Stmt;
+
+pub const VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct ProgramRef<'a> {
+    version: u32,
+    statements: &'a [Stmt],
+}
+
+#[derive(Deserialize)]
+struct Program {
+    version: u32,
+    statements: Vec<Stmt>,
+}
+
+pub fn to_json(statements: &[Stmt]) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&ProgramRef {
+        version: VERSION,
+        statements,
+    })
+}
+
+pub fn from_json(json: &str) -> Result<Vec<Stmt>, serde_json::Error> {
+    let program: Program = serde_json::from_str(json)?;
+
+    if program.version != VERSION {
+        return Err(serde_json::Error::custom(format!(
+            "unsupported AST version {}, expected {}",
+            program.version, VERSION
+        )));
+    }
+
+    Ok(program.statements)
+}
+
+/// Literals are stored as plain JSON scalars, with `null` standing for `nil`.
+pub mod literal {
+    use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serializer};
+
+    use crate::value::Value;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Literal {
+        Bool(bool),
+        Number(f64),
+        String(String),
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Value>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            None | Some(Value::Nil) => serializer.serialize_none(),
+            Some(Value::Bool(b)) => serializer.serialize_bool(*b),
+            Some(Value::Number(n)) => serializer.serialize_f64(*n),
+            Some(Value::String(s)) => serializer.serialize_str(s),
+            Some(value) => Err(S::Error::custom(format!(
+                "{} cannot appear as a literal",
+                value
+            ))),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Value>, D::Error> {
+        let literal = Option::<Literal>::deserialize(deserializer).map_err(|_| {
+            D::Error::custom("literal must be null, a boolean, a number or a string")
+        })?;
+
+        Ok(literal.map(|literal| match literal {
+            Literal::Bool(b) => Value::Bool(b),
+            Literal::Number(n) => Value::Number(n),
+            Literal::String(s) => Value::String(s),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{parser::Parser, printer::AstPrinter},
+        capture::Capture,
+        interpreter::Interpreter,
+        lexer::scanner::Scanner,
+    };
+
+    const SOURCE: &str = r#"
+var greeting = "hi";
+{
+  var n = -(1 + 2) * 3;
+  if (n < 0 and !false) { print greeting; } else { n = nil; }
+}
+test "t" { assertEq(len("ab"), 2); }
+"#;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let (tokens, _) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        let (statements, errors) = Parser::new(tokens).parse_with_errors();
+        assert!(errors.is_empty(), "{:?}", errors);
+        statements
+    }
+
+    #[test]
+    fn round_trips_every_node_kind() {
+        let statements = parse(SOURCE);
+        let json = to_json(&statements).unwrap();
+        let restored = from_json(&json).unwrap();
+
+        assert_eq!(
+            AstPrinter::new().print(&restored),
+            AstPrinter::new().print(&statements)
+        );
+        assert_eq!(to_json(&restored).unwrap(), json);
+    }
+
+    #[test]
+    fn restored_trees_run() {
+        let json = to_json(&parse("var x = 2; print x * 21;")).unwrap();
+        let output = Capture::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(output.clone());
+
+        interpreter.interpret(&from_json(&json).unwrap());
+        assert_eq!(output.contents(), "42\n");
+    }
+
+    #[test]
+    fn records_kinds_tokens_and_positions() {
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&parse("print 1.5;")).unwrap()).unwrap();
+
+        assert_eq!(json["version"], VERSION);
+        let print = &json["statements"][0];
+        assert_eq!(print["kind"], "Print");
+        assert_eq!(print["keyword"]["line"], 1);
+        assert_eq!(print["keyword"]["column"], 1);
+        assert_eq!(print["expression"]["kind"], "Literal");
+        assert_eq!(print["expression"]["value"], 1.5);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let err = from_json(r#"{"version": 1, "statements": []}"#)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("unsupported AST version 1, expected 2"));
+    }
+
+    #[test]
+    fn rejects_literals_that_are_not_scalars() {
+        let json = to_json(&parse("1;"))
+            .unwrap()
+            .replace("\"value\": 1.0", "\"value\": [1]");
+        let err = from_json(&json).err().unwrap();
+        assert!(err.to_string().contains("literal must be null"), "{}", err);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod expr;
+pub mod json;
 pub mod parser;
+pub mod printer;
 pub mod statement;
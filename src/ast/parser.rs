@@ -1,35 +1,85 @@
+use std::cell::RefCell;
+
 use crate::{
     ast::expr::{GroupingExpr, LiteralExpr},
-    error::{self, ParseError},
+    error::{self, ParseError, SyntaxError},
     lexer::{token::Token, token_type::TokenType},
     value::Value,
 };
 
 use super::{
-    expr::{AssignExpr, BinaryExpr, Expr, LogicalExpr, UnaryExpr, VariableExpr},
-    statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, Stmt, VarStmt},
+    expr::{AssignExpr, BinaryExpr, CallExpr, Expr, LogicalExpr, UnaryExpr, VariableExpr},
+    statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, Stmt, TestStmt, VarStmt},
 };
 
+/// How deeply statements and expressions may nest before parsing gives up.
+/// A parenthesis takes around 20 KiB of stack to parse in a debug build, so
+/// this stays well inside the 2 MiB of a test thread. Nesting is counted as the
+/// interpreter counts it against `Limits::depth`, so a chain like `a + b + c`
+/// nests a level per operator.
+pub const MAX_NESTING: usize = 64;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: RefCell<Vec<SyntaxError>>,
+    nesting: usize,
+    /// The deepest `nesting` reached while parsing the current operand.
+    deepest: usize,
 }
 
+// Errors are propagated with explicit matches throughout the parser.
+#[allow(clippy::question_mark)]
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: RefCell::new(Vec::new()),
+            nesting: 0,
+            deepest: 0,
+        }
     }
 
     pub fn parse(&mut self) -> Vec<Stmt> {
+        let (statements, errors) = self.parse_with_errors();
+
+        for err in errors {
+            error::error_token(&err.token, &err.message);
+        }
+
+        statements
+    }
+
+    /// Parses like `parse`, but hands back the errors instead of reporting them.
+    pub fn parse_with_errors(&mut self) -> (Vec<Stmt>, Vec<SyntaxError>) {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
-            if let Ok(stmt) = self.declaration() {
+            if let Ok(stmt) = self.top_level_declaration() {
                 statements.push(stmt)
             }
         }
 
-        statements
+        (statements, self.errors.take())
+    }
+
+    /// Parses the tokens as a single expression, as typed at a debugger prompt.
+    /// Returns the first error if they are anything else.
+    pub fn parse_expression(&mut self) -> Result<Expr, SyntaxError> {
+        let expr = self.expression().and_then(|expr| {
+            if self.is_at_end() {
+                Ok(expr)
+            } else {
+                Err(self.error(self.peek(), "Expected end of expression."))
+            }
+        });
+
+        let mut errors = self.errors.take();
+        match expr {
+            Ok(expr) if errors.is_empty() => Ok(expr),
+            _ => Err(errors.remove(0)),
+        }
     }
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
@@ -42,8 +92,9 @@ impl Parser {
         }
 
         if self.match_token(&[TokenType::LeftBrace]) {
+            let brace = self.previous().clone();
             match self.block() {
-                Ok(block) => return Ok(Stmt::Block(BlockStmt::new(block))),
+                Ok(block) => return Ok(Stmt::Block(BlockStmt::new(brace, block))),
                 Err(e) => return Err(e),
             }
         }
@@ -92,12 +143,50 @@ impl Parser {
         Ok(statements)
     }
 
+    /// A declaration, or a `test` declaration, which may only appear at the top
+    /// level. `test` is only a keyword when a string follows it.
+    fn top_level_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let is_test = self.check(&TokenType::Identifier)
+            && self.peek().lexeme == "test"
+            && matches!(
+                self.tokens.get(self.current + 1),
+                Some(token) if token.token_type == TokenType::String
+            );
+
+        if !is_test {
+            return self.declaration();
+        }
+
+        let keyword = self.advance().clone();
+        match self.test_declaration(keyword) {
+            Ok(stmt) => Ok(stmt),
+            Err(err) => {
+                self.synchronize();
+                Err(err)
+            }
+        }
+    }
+
+    fn test_declaration(&mut self, keyword: Token) -> Result<Stmt, ParseError> {
+        let name = self.advance().clone();
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before test body.")?;
+        let brace = self.previous().clone();
+        let body = self.block()?;
+
+        Ok(Stmt::Test(TestStmt::new(
+            keyword,
+            name,
+            Stmt::Block(BlockStmt::new(brace, body)),
+        )))
+    }
+
     fn declaration(&mut self) -> Result<Stmt, ParseError> {
         if self.match_token(&[TokenType::Var]) {
             return self.var_declaration();
         }
 
-        match self.statement() {
+        match self.nested(Self::statement) {
             Ok(stmt) => Ok(stmt),
             Err(err) => {
                 self.synchronize();
@@ -135,35 +224,37 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
         let expr = match self.expression() {
             Ok(expr) => expr,
             Err(err) => return Err(err),
         };
 
         match self.consume(TokenType::Semicolon, "Expect ';' after value.") {
-            Ok(_) => Ok(Stmt::Print(PrintStmt::new(expr))),
+            Ok(_) => Ok(Stmt::Print(PrintStmt::new(keyword, expr))),
             Err(err) => Err(err),
         }
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = match self.expression() {
-            Ok(expr) => expr,
+            Ok(condition) => condition,
             Err(err) => return Err(err),
         };
         self.consume(TokenType::RightParen, "Expect ')' after 'if' condition.")?;
 
         self.consume(TokenType::LeftBrace, "Expect '{' before 'then' body.")?;
-        let then_branch = match self.statement() {
-            Ok(stmt) => stmt,
+        let then_branch = match self.nested(Self::statement) {
+            Ok(then_branch) => then_branch,
             Err(err) => return Err(err),
         };
         self.consume(TokenType::RightBrace, "Expect '}' after 'then' body.")?;
 
         let else_branch = if self.match_token(&[TokenType::Else]) {
             self.consume(TokenType::LeftBrace, "Expect '{' before 'else' body.")?;
-            let else_branch = match self.statement() {
+            let else_branch = match self.nested(Self::statement) {
                 Ok(stmt) => Some(stmt),
                 Err(err) => return Err(err),
             };
@@ -174,7 +265,12 @@ impl Parser {
             None
         };
 
-        Ok(Stmt::If(IfStmt::new(condition, then_branch, else_branch)))
+        Ok(Stmt::If(IfStmt::new(
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        )))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
@@ -190,19 +286,18 @@ impl Parser {
     }
 
     fn expression(&mut self) -> Result<Expr, ParseError> {
-        self.assignment()
+        self.nested(Self::assignment)
     }
 
     fn assignment(&mut self) -> Result<Expr, ParseError> {
-        let expr_result = self.or();
-        let expr = match expr_result.clone() {
+        let expr = match self.or() {
             Ok(expr) => expr,
             Err(err) => return Err(err),
         };
 
         if self.match_token(&[TokenType::Equal]) {
             let equals = self.previous().clone();
-            let value = match self.assignment() {
+            let value = match self.nested(Self::assignment) {
                 Ok(value) => value,
                 Err(err) => return Err(err),
             };
@@ -211,83 +306,84 @@ impl Parser {
                 return Ok(Expr::Assign(AssignExpr::new(var.name, value)));
             }
 
-            error::error_token(&equals, "Invalid assignment target.")
+            self.error(&equals, "Invalid assignment target.");
+            return Ok(expr);
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn or(&mut self) -> Result<Expr, ParseError> {
-        let mut expr_result = self.and();
-        let expr = match expr_result.clone() {
-            Ok(expr) => expr,
+        let (mut expr, mut depth) = match self.operand(Self::and) {
+            Ok(operand) => operand,
             Err(err) => return Err(err),
         };
 
         while self.match_token(&[TokenType::Or]) {
             let operator = self.previous().clone();
-            let right = match self.and() {
-                Ok(right) => right,
+            let (right, right_depth) = match self.operand(Self::and) {
+                Ok(operand) => operand,
+                Err(err) => return Err(err),
+            };
+            depth = match self.chained(&operator, depth, right_depth) {
+                Ok(depth) => depth,
                 Err(err) => return Err(err),
             };
 
-            expr_result = Ok(Expr::Logical(LogicalExpr::new(
-                operator,
-                expr.clone(),
-                right,
-            )));
+            expr = Expr::Logical(LogicalExpr::new(operator, expr, right));
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn and(&mut self) -> Result<Expr, ParseError> {
-        let mut expr_result = self.equality();
-        let expr = match expr_result.clone() {
-            Ok(expr) => expr,
+        let (mut expr, mut depth) = match self.operand(Self::equality) {
+            Ok(operand) => operand,
             Err(err) => return Err(err),
         };
 
         while self.match_token(&[TokenType::And]) {
             let operator = self.previous().clone();
-            let right = match self.equality() {
-                Ok(right) => right,
+            let (right, right_depth) = match self.operand(Self::equality) {
+                Ok(operand) => operand,
+                Err(err) => return Err(err),
+            };
+            depth = match self.chained(&operator, depth, right_depth) {
+                Ok(depth) => depth,
                 Err(err) => return Err(err),
             };
 
-            expr_result = Ok(Expr::Logical(LogicalExpr::new(
-                operator,
-                expr.clone(),
-                right,
-            )));
+            expr = Expr::Logical(LogicalExpr::new(operator, expr, right));
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn equality(&mut self) -> Result<Expr, ParseError> {
-        let mut expr_result = self.comparison();
-        let expr = match expr_result.clone() {
-            Ok(expr) => expr,
+        let (mut expr, mut depth) = match self.operand(Self::comparison) {
+            Ok(operand) => operand,
             Err(err) => return Err(err),
         };
 
         while self.match_token(&[TokenType::BangEqual, TokenType::EqualEqual]) {
             let operator = self.previous().clone();
-            let right = match self.comparison() {
-                Ok(expr) => expr,
+            let (right, right_depth) = match self.operand(Self::comparison) {
+                Ok(operand) => operand,
                 Err(err) => return Err(err),
             };
-            expr_result = Ok(Expr::Binary(BinaryExpr::new(operator, expr.clone(), right)));
+            depth = match self.chained(&operator, depth, right_depth) {
+                Ok(depth) => depth,
+                Err(err) => return Err(err),
+            };
+            expr = Expr::Binary(BinaryExpr::new(operator, expr, right));
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn comparison(&mut self) -> Result<Expr, ParseError> {
-        let mut expr_result = self.term();
-        let expr = match expr_result.clone() {
-            Ok(expr) => expr,
+        let (mut expr, mut depth) = match self.operand(Self::term) {
+            Ok(operand) => operand,
             Err(err) => return Err(err),
         };
 
@@ -298,79 +394,132 @@ impl Parser {
             TokenType::LessEqual,
         ]) {
             let operator = self.previous().clone();
-            let right = match self.term() {
-                Ok(expr) => expr,
+            let (right, right_depth) = match self.operand(Self::term) {
+                Ok(operand) => operand,
+                Err(err) => return Err(err),
+            };
+            depth = match self.chained(&operator, depth, right_depth) {
+                Ok(depth) => depth,
                 Err(err) => return Err(err),
             };
-            expr_result = Ok(Expr::Binary(BinaryExpr::new(operator, expr.clone(), right)));
+            expr = Expr::Binary(BinaryExpr::new(operator, expr, right));
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn term(&mut self) -> Result<Expr, ParseError> {
-        let mut expr_result = self.factor();
-        let expr = match expr_result.clone() {
-            Ok(expr) => expr,
+        let (mut expr, mut depth) = match self.operand(Self::factor) {
+            Ok(operand) => operand,
             Err(err) => return Err(err),
         };
 
         while self.match_token(&[TokenType::Minus, TokenType::Plus]) {
             let operator = self.previous().clone();
-            let right = match self.factor() {
-                Ok(expr) => expr,
+            let (right, right_depth) = match self.operand(Self::factor) {
+                Ok(operand) => operand,
                 Err(err) => return Err(err),
             };
-            expr_result = Ok(Expr::Binary(BinaryExpr::new(operator, expr.clone(), right)));
+            depth = match self.chained(&operator, depth, right_depth) {
+                Ok(depth) => depth,
+                Err(err) => return Err(err),
+            };
+            expr = Expr::Binary(BinaryExpr::new(operator, expr, right));
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn factor(&mut self) -> Result<Expr, ParseError> {
-        let mut expr_result = self.unary();
-        let expr = match expr_result.clone() {
-            Ok(expr) => expr,
+        let (mut expr, mut depth) = match self.operand(Self::unary) {
+            Ok(operand) => operand,
             Err(err) => return Err(err),
         };
 
         while self.match_token(&[TokenType::Slash, TokenType::Star]) {
             let operator = self.previous().clone();
-            let right = match self.unary() {
-                Ok(expr) => expr,
+            let (right, right_depth) = match self.operand(Self::unary) {
+                Ok(operand) => operand,
+                Err(err) => return Err(err),
+            };
+            depth = match self.chained(&operator, depth, right_depth) {
+                Ok(depth) => depth,
                 Err(err) => return Err(err),
             };
-            expr_result = Ok(Expr::Binary(BinaryExpr::new(operator, expr.clone(), right)));
+            expr = Expr::Binary(BinaryExpr::new(operator, expr, right));
         }
 
-        expr_result
+        Ok(expr)
     }
 
     fn unary(&mut self) -> Result<Expr, ParseError> {
         if self.match_token(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous().clone();
-            let right = match self.unary() {
-                Ok(expr) => expr,
+            let right = match self.nested(Self::unary) {
+                Ok(right) => right,
                 Err(err) => return Err(err),
             };
             return Ok(Expr::Unary(UnaryExpr::new(operator, right)));
         }
 
-        match self.primary() {
-            Ok(expr) => Ok(expr),
-            Err(err) => Err(err),
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let (mut expr, mut depth) = self.operand(Self::primary)?;
+
+        while self.match_token(&[TokenType::LeftParen]) {
+            let paren = self.previous().clone();
+            let (call, arguments_depth) = self.operand(|parser| parser.finish_call(expr))?;
+            depth = self.chained(&paren, depth, arguments_depth)?;
+            expr = call;
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = Vec::new();
+
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    self.error(self.peek(), "Can't have more than 255 arguments.");
+                }
+
+                arguments.push(self.expression()?);
+
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
         }
+
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+
+        Ok(Expr::Call(CallExpr::new(callee, paren, arguments)))
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
         if self.match_token(&[TokenType::False]) {
-            return Ok(Expr::Literal(LiteralExpr::new(Value::Bool(false))));
+            return Ok(Expr::Literal(LiteralExpr::new(
+                self.previous().clone(),
+                Value::Bool(false),
+            )));
         }
         if self.match_token(&[TokenType::True]) {
-            return Ok(Expr::Literal(LiteralExpr::new(Value::Bool(true))));
+            return Ok(Expr::Literal(LiteralExpr::new(
+                self.previous().clone(),
+                Value::Bool(true),
+            )));
         }
         if self.match_token(&[TokenType::Nil]) {
-            return Ok(Expr::Literal(LiteralExpr::new(Value::Nil)));
+            return Ok(Expr::Literal(LiteralExpr::new(
+                self.previous().clone(),
+                Value::Nil,
+            )));
         }
 
         if self.match_token(&[TokenType::Number, TokenType::String]) {
@@ -379,7 +528,10 @@ impl Parser {
                 None => return Err(self.error(self.peek(), "Expected literal value.")),
             };
 
-            return Ok(Expr::Literal(LiteralExpr::new(value)));
+            return Ok(Expr::Literal(LiteralExpr::new(
+                self.previous().clone(),
+                value,
+            )));
         }
 
         if self.match_token(&[TokenType::LeftParen]) {
@@ -408,8 +560,60 @@ impl Parser {
         Err(self.error(self.peek(), message))
     }
 
+    /// Runs `parse` one nesting level deeper, failing once `MAX_NESTING` is reached.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.nesting >= MAX_NESTING {
+            return Err(self.error(self.peek(), "Too much nesting."));
+        }
+
+        self.nesting += 1;
+        self.deepest = self.deepest.max(self.nesting);
+        let result = parse(self);
+        self.nesting -= 1;
+
+        result
+    }
+
+    /// Runs `parse` for an operand, returning it along with how many levels
+    /// below the current one it nests.
+    fn operand(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<(Expr, usize), ParseError> {
+        let outer = std::mem::replace(&mut self.deepest, self.nesting);
+        let result = parse(self);
+        let depth = self.deepest - self.nesting;
+        self.deepest = self.deepest.max(outer);
+
+        result.map(|expr| (expr, depth))
+    }
+
+    /// Accounts for one more link in a chain such as `a + b + c`, which nests
+    /// its left operand a level deeper each time, given how deeply the two
+    /// operands of the new link nest. Fails past `MAX_NESTING`, like `nested`.
+    fn chained(
+        &mut self,
+        operator: &Token,
+        left: usize,
+        right: usize,
+    ) -> Result<usize, ParseError> {
+        let depth = left.max(right) + 1;
+        if self.nesting + depth > MAX_NESTING {
+            return Err(self.error(operator, "Too much nesting."));
+        }
+
+        self.deepest = self.deepest.max(self.nesting + depth);
+        Ok(depth)
+    }
+
     fn error(&self, token: &Token, message: &str) -> ParseError {
-        error::error_token(token, message);
+        self.errors.borrow_mut().push(SyntaxError {
+            token: token.clone(),
+            message: String::from(message),
+        });
         ParseError {}
     }
 
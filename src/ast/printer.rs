@@ -0,0 +1,191 @@
+use crate::{expr_visitor::ExprVisitor, stmt_visitor::StmtVisitor, value::Value};
+
+use super::{
+    expr::{
+        AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, LiteralExpr, LogicalExpr, UnaryExpr,
+        VariableExpr,
+    },
+    statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, Stmt, TestStmt, VarStmt},
+};
+
+/// Renders the AST as parenthesised S-expressions, e.g. `(+ 1 (* 2 3))`, making
+/// the grouping chosen by the parser explicit.
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn print(&mut self, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|stmt| self.print_stmt(stmt))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    pub fn print_expr(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
+        let mut result = format!("({}", name);
+
+        for expr in exprs {
+            result.push(' ');
+            result.push_str(&self.print_expr(expr));
+        }
+
+        result.push(')');
+        result
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> String {
+        let mut result = String::from("(block");
+
+        for statement in &stmt.statements {
+            result.push(' ');
+            result.push_str(&self.print_stmt(statement));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> String {
+        self.parenthesize(";", &[&stmt.expression])
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> String {
+        let condition = self.print_expr(&stmt.condition);
+        let then_branch = self.print_stmt(&stmt.then_branch);
+
+        match &stmt.else_branch {
+            Some(else_branch) => format!(
+                "(if {} {} {})",
+                condition,
+                then_branch,
+                self.print_stmt(else_branch)
+            ),
+            None => format!("(if {} {})", condition, then_branch),
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> String {
+        self.parenthesize("print", &[&stmt.expression])
+    }
+
+    fn visit_test_stmt(&mut self, stmt: &TestStmt) -> String {
+        format!(
+            "(test {} {})",
+            stmt.name.lexeme,
+            self.print_stmt(&stmt.body)
+        )
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> String {
+        match &stmt.initializer {
+            Some(initializer) => {
+                format!(
+                    "(var {} {})",
+                    stmt.name.lexeme,
+                    self.print_expr(initializer)
+                )
+            }
+            None => format!("(var {})", stmt.name.lexeme),
+        }
+    }
+}
+
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> String {
+        format!("(= {} {})", expr.name.lexeme, self.print_expr(&expr.value))
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> String {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> String {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> String {
+        self.parenthesize("group", &[&expr.expression])
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> String {
+        match &expr.value {
+            Some(Value::String(s)) => format!("{:?}", s),
+            Some(value) => value.to_string(),
+            None => String::from("nil"),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> String {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> String {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.right])
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> String {
+        expr.name.lexeme.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::parser::Parser, lexer::scanner::Scanner};
+
+    fn print(source: &str) -> String {
+        let (tokens, _) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        let (statements, errors) = Parser::new(tokens).parse_with_errors();
+        assert!(errors.is_empty(), "{:?}", errors);
+        AstPrinter::new().print(&statements)
+    }
+
+    #[test]
+    fn prints_expressions_as_s_expressions() {
+        assert_eq!(
+            print("-1 + 2 * (3 - x);"),
+            "(; (+ (- 1) (* 2 (group (- 3 x)))))"
+        );
+        assert_eq!(
+            print("a = b or !c and nil;"),
+            "(; (= a (or b (and (! c) nil))))"
+        );
+        assert_eq!(
+            print("f(1, \"s\")(true);"),
+            "(; (call (call f 1 \"s\") true))"
+        );
+    }
+
+    #[test]
+    fn prints_statements() {
+        assert_eq!(
+            print("var x = 1; { print x; } if (x) { x; } else { nil; }"),
+            "(var x 1)\n(block (print x))\n(if x (; x) (; nil))"
+        );
+    }
+
+    #[test]
+    fn binary_operators_group_to_the_left() {
+        assert_eq!(print("1 - 2 - 3;"), "(; (- (- 1 2) 3))");
+        assert_eq!(print("8 / 4 / 2 * 3;"), "(; (* (/ (/ 8 4) 2) 3))");
+        assert_eq!(print("a == b != c;"), "(; (!= (== a b) c))");
+        assert_eq!(print("a or b or c;"), "(; (or (or a b) c))");
+    }
+}
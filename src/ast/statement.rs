@@ -1,12 +1,17 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{lexer::token::Token, stmt_visitor::StmtVisitor};
 
 use super::expr::Expr;
 
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind")]
 pub enum Stmt {
     Block(BlockStmt),
     Expression(ExpressionStmt),
     If(IfStmt),
     Print(PrintStmt),
+    Test(TestStmt),
     Var(VarStmt),
 }
 
@@ -17,37 +22,68 @@ impl Stmt {
             Stmt::Expression(stmt) => visitor.visit_expression_stmt(stmt),
             Stmt::If(stmt) => visitor.visit_if_stmt(stmt),
             Stmt::Print(stmt) => visitor.visit_print_stmt(stmt),
+            Stmt::Test(stmt) => visitor.visit_test_stmt(stmt),
             Stmt::Var(stmt) => visitor.visit_var_stmt(stmt),
         }
     }
+
+    /// The earliest token the statement keeps, which gives its position: the
+    /// keyword or opening brace, the name for `var` (whose keyword is not kept),
+    /// or the first token of an expression statement's expression.
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Stmt::Block(stmt) => &stmt.brace,
+            Stmt::Expression(stmt) => stmt.expression.first_token(),
+            Stmt::If(stmt) => &stmt.keyword,
+            Stmt::Print(stmt) => &stmt.keyword,
+            Stmt::Test(stmt) => &stmt.keyword,
+            Stmt::Var(stmt) => &stmt.name,
+        }
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct BlockStmt {
+    pub brace: Token,
     pub statements: Vec<Stmt>,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct ExpressionStmt {
     pub expression: Expr,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct IfStmt {
+    pub keyword: Token,
     pub condition: Expr,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct PrintStmt {
+    pub keyword: Token,
     pub expression: Expr,
 }
 
+/// `test "name" { ... }`. Only `rslox test` runs the body.
+#[derive(Serialize, Deserialize)]
+pub struct TestStmt {
+    pub keyword: Token,
+    pub name: Token,
+    pub body: Box<Stmt>,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct VarStmt {
     pub name: Token,
     pub initializer: Option<Expr>,
 }
 
 impl BlockStmt {
-    pub fn new(statements: Vec<Stmt>) -> Self {
-        Self { statements }
+    pub fn new(brace: Token, statements: Vec<Stmt>) -> Self {
+        Self { brace, statements }
     }
 }
 
@@ -58,8 +94,14 @@ impl ExpressionStmt {
 }
 
 impl IfStmt {
-    pub fn new(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Self {
+    pub fn new(
+        keyword: Token,
+        condition: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Self {
         Self {
+            keyword,
             condition,
             then_branch: Box::new(then_branch),
             else_branch: else_branch.map(Box::new),
@@ -68,8 +110,21 @@ impl IfStmt {
 }
 
 impl PrintStmt {
-    pub fn new(expression: Expr) -> Self {
-        Self { expression }
+    pub fn new(keyword: Token, expression: Expr) -> Self {
+        Self {
+            keyword,
+            expression,
+        }
+    }
+}
+
+impl TestStmt {
+    pub fn new(keyword: Token, name: Token, body: Stmt) -> Self {
+        Self {
+            keyword,
+            name,
+            body: Box::new(body),
+        }
     }
 }
 
@@ -103,6 +158,12 @@ impl From<PrintStmt> for Stmt {
     }
 }
 
+impl From<TestStmt> for Stmt {
+    fn from(statement: TestStmt) -> Self {
+        Stmt::Test(statement)
+    }
+}
+
 impl From<VarStmt> for Stmt {
     fn from(statement: VarStmt) -> Self {
         Stmt::Var(statement)
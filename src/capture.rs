@@ -0,0 +1,47 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+/// A `Write` that collects everything written to it, for capturing a script's
+/// output or diagnostics as a string. Clones share the same buffer, so one can
+/// be handed to the interpreter and another kept to read from.
+///
+/// ```
+/// use rslox::{Capture, Lox};
+///
+/// let output = Capture::new();
+/// let mut lox = Lox::new();
+/// lox.set_output(output.clone());
+///
+/// lox.eval("print 1 + 2;").unwrap();
+/// assert_eq!(output.contents(), "3\n");
+/// ```
+#[derive(Clone, Default)]
+pub struct Capture {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).into_owned()
+    }
+
+    /// Returns what has been written so far and empties the buffer.
+    pub fn take(&self) -> String {
+        let bytes = self.buffer.take();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
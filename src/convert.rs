@@ -0,0 +1,271 @@
+//! Conversions between Rust values and Lox `Value`s for embedding hosts.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::value::Value;
+
+/// Why a `Value` could not be converted. `expected` reads as the end of a
+/// sentence, e.g. "a number" or "an integer between 0 and 255".
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: String,
+}
+
+impl ConversionError {
+    pub fn new(expected: impl Into<String>) -> Self {
+        Self {
+            expected: expected.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Expected {}.", self.expected)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+pub trait FromLox: Sized {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError>;
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl FromLox for Value {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromLox for bool {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(ConversionError::new("a boolean")),
+        }
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromLox for f64 {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            _ => Err(ConversionError::new("a number")),
+        }
+    }
+}
+
+impl IntoLox for f32 {
+    fn into_lox(self) -> Value {
+        Value::Number(self as f64)
+    }
+}
+
+impl FromLox for f32 {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        f64::from_lox(value).map(|n| n as f32)
+    }
+}
+
+/// Integers convert from numbers that are whole and in range.
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl IntoLox for $t {
+                fn into_lox(self) -> Value {
+                    Value::Number(self as f64)
+                }
+            }
+
+            impl FromLox for $t {
+                fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+                    match value {
+                        // `MAX as f64 + 1.0` is exact where `MAX as f64` may round up.
+                        Value::Number(n)
+                            if n.fract() == 0.0
+                                && *n >= <$t>::MIN as f64
+                                && *n < <$t>::MAX as f64 + 1.0 =>
+                        {
+                            Ok(*n as $t)
+                        }
+                        _ => Err(ConversionError::new(format!(
+                            "an integer between {} and {}",
+                            <$t>::MIN,
+                            <$t>::MAX
+                        ))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::String(String::from(self))
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(ConversionError::new("a string")),
+        }
+    }
+}
+
+/// `None` is `nil`.
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        match self {
+            Some(value) => value.into_lox(),
+            None => Value::Nil,
+        }
+    }
+}
+
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::from_lox(value)
+                .map(Some)
+                .map_err(|err| ConversionError::new(format!("nil or {}", err.expected))),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        Value::list(self.into_iter().map(IntoLox::into_lox).collect())
+    }
+}
+
+impl<T: FromLox> FromLox for Vec<T> {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        let list = match value {
+            Value::List(list) => list.borrow(),
+            _ => return Err(ConversionError::new("a list")),
+        };
+
+        list.iter()
+            .enumerate()
+            .map(|(index, value)| {
+                T::from_lox(value).map_err(|err| {
+                    ConversionError::new(format!(
+                        "a list whose element {} is {}",
+                        index, err.expected
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+impl<T: IntoLox> IntoLox for HashMap<String, T> {
+    fn into_lox(self) -> Value {
+        Value::map(
+            self.into_iter()
+                .map(|(key, value)| (key, value.into_lox()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: FromLox> FromLox for HashMap<String, T> {
+    fn from_lox(value: &Value) -> Result<Self, ConversionError> {
+        let map = match value {
+            Value::Map(map) => map.borrow(),
+            _ => return Err(ConversionError::new("a map")),
+        };
+
+        map.iter()
+            .map(|(key, value)| Ok((key.clone(), field(value, key)?)))
+            .collect()
+    }
+}
+
+impl<T: IntoLox> IntoLox for BTreeMap<String, T> {
+    fn into_lox(self) -> Value {
+        Value::map(
+            self.into_iter()
+                .map(|(key, value)| (key, value.into_lox()))
+                .collect(),
+        )
+    }
+}
+
+/// Converts the entry `key` of a map, which is `nil` if it is missing. Used by
+/// `#[derive(FromLox)]`.
+pub fn field<T: FromLox>(value: &Value, key: &str) -> Result<T, ConversionError> {
+    T::from_lox(value)
+        .map_err(|err| ConversionError::new(format!("a map whose '{}' is {}", key, err.expected)))
+}
+
+/// The arguments of a typed host function, converted from the values the
+/// script passed. Implemented for tuples of up to six `FromLox` types.
+pub trait FromArgs: Sized {
+    const ARITY: usize;
+
+    /// Converts `args`, or returns the index of the first argument that does not
+    /// convert along with the reason.
+    fn from_args(args: &[Value]) -> Result<Self, (usize, ConversionError)>;
+}
+
+macro_rules! from_args {
+    ($arity:expr; $($t:ident $index:tt),*) => {
+        impl<$($t: FromLox),*> FromArgs for ($($t,)*) {
+            const ARITY: usize = $arity;
+
+            #[allow(unused_variables)]
+            fn from_args(args: &[Value]) -> Result<Self, (usize, ConversionError)> {
+                Ok(($($t::from_lox(&args[$index]).map_err(|err| ($index, err))?,)*))
+            }
+        }
+    };
+}
+
+from_args!(0;);
+from_args!(1; A 0);
+from_args!(2; A 0, B 1);
+from_args!(3; A 0, B 1, C 2);
+from_args!(4; A 0, B 1, C 2, D 3);
+from_args!(5; A 0, B 1, C 2, D 3, E 4);
+from_args!(6; A 0, B 1, C 2, D 3, E 4, F 5);
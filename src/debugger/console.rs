@@ -0,0 +1,300 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    ast::statement::Stmt,
+    debugger::{self, Hook, Resume, Stepper, Stop},
+    error::RuntimeError,
+    interpreter::Interpreter,
+};
+
+const HELP: &str = "\
+break LINE     (b)  set a breakpoint
+clear LINE          remove a breakpoint
+breakpoints         list breakpoints
+continue       (c)  run to the next breakpoint
+step           (s)  step into the next statement
+next           (n)  step over the current statement
+out            (o)  step out of the enclosing statement
+env            (e)  print the variables in scope
+print EXPR     (p)  evaluate an expression in the current scope
+where          (w)  show the current line
+quit           (q)  stop the script";
+
+/// A line-oriented debugger that reads commands from `input` and answers on
+/// `output`. It pauses before the first statement. At end of input it lets the
+/// script run to completion.
+pub struct Console {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    prompt: bool,
+    lines: Vec<String>,
+    stepper: Stepper,
+}
+
+impl Console {
+    pub fn new(
+        source: &str,
+        input: Box<dyn BufRead>,
+        output: Box<dyn Write>,
+        prompt: bool,
+    ) -> Self {
+        Self {
+            input,
+            output,
+            prompt,
+            lines: source.lines().map(String::from).collect(),
+            stepper: Stepper::new(true),
+        }
+    }
+
+    fn show(&mut self, line: usize) {
+        let text = self.lines.get(line - 1).map_or("", |text| text.trim());
+        let _ = writeln!(self.output, "[line {}] {}", line, text);
+    }
+
+    fn read_command(&mut self) -> Option<String> {
+        if self.prompt {
+            let _ = write!(self.output, "(debug) ");
+            let _ = self.output.flush();
+        }
+
+        let mut command = String::new();
+        match self.input.read_line(&mut command) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(command.trim().to_string()),
+        }
+    }
+
+    fn line_argument(&mut self, argument: &str) -> Option<usize> {
+        match argument.parse::<usize>() {
+            Ok(line) if line > 0 => Some(line),
+            _ => {
+                let _ = writeln!(
+                    self.output,
+                    "Expected a line number but got '{}'.",
+                    argument
+                );
+                None
+            }
+        }
+    }
+}
+
+impl Hook for Console {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), RuntimeError> {
+        let line = stmt.first_token().line;
+        let depth = interpreter.depth();
+
+        match self.stepper.should_stop(line, depth) {
+            Some(Stop::Breakpoint) => {
+                let _ = writeln!(self.output, "Breakpoint at line {}.", line);
+            }
+            Some(_) => {}
+            None => return Ok(()),
+        }
+        self.show(line);
+
+        loop {
+            let command = match self.read_command() {
+                Some(command) => command,
+                None => {
+                    self.stepper.breakpoints.clear();
+                    self.stepper.resume(Resume::Continue, depth);
+                    return Ok(());
+                }
+            };
+
+            let (name, argument) = match command.split_once(char::is_whitespace) {
+                Some((name, argument)) => (name, argument.trim()),
+                None => (command.as_str(), ""),
+            };
+
+            match name {
+                "" => {}
+                "break" | "b" => {
+                    if let Some(line) = self.line_argument(argument) {
+                        self.stepper.breakpoints.insert(line);
+                        let _ = writeln!(self.output, "Breakpoint set at line {}.", line);
+                    }
+                }
+                "clear" => {
+                    if let Some(line) = self.line_argument(argument) {
+                        if self.stepper.breakpoints.remove(&line) {
+                            let _ = writeln!(self.output, "Breakpoint cleared at line {}.", line);
+                        } else {
+                            let _ = writeln!(self.output, "No breakpoint at line {}.", line);
+                        }
+                    }
+                }
+                "breakpoints" => {
+                    for line in &self.stepper.breakpoints {
+                        let _ = writeln!(self.output, "line {}", line);
+                    }
+                }
+                "continue" | "c" => {
+                    self.stepper.resume(Resume::Continue, depth);
+                    return Ok(());
+                }
+                "step" | "s" => {
+                    self.stepper.resume(Resume::StepIn, depth);
+                    return Ok(());
+                }
+                "next" | "n" => {
+                    self.stepper.resume(Resume::StepOver, depth);
+                    return Ok(());
+                }
+                "out" | "o" => {
+                    self.stepper.resume(Resume::StepOut, depth);
+                    return Ok(());
+                }
+                "env" | "e" => {
+                    let scopes = debugger::scopes(&interpreter.environment);
+                    let count = scopes.len();
+                    for (index, variables) in scopes.into_iter().enumerate() {
+                        let _ = match count - index - 1 {
+                            0 => writeln!(self.output, "global:"),
+                            level => writeln!(self.output, "block {}:", level),
+                        };
+                        for (name, value) in variables {
+                            let _ = writeln!(self.output, "  {} = {}", name, value);
+                        }
+                    }
+                }
+                "print" | "p" => {
+                    let _ = match debugger::evaluate(interpreter, argument) {
+                        Ok(value) => writeln!(self.output, "{}", value),
+                        Err(message) => writeln!(self.output, "Error: {}", message),
+                    };
+                }
+                "where" | "w" => self.show(line),
+                "help" | "h" => {
+                    let _ = writeln!(self.output, "{}", HELP);
+                }
+                "quit" | "q" => return Err(RuntimeError::exit(stmt.first_token().clone(), 0)),
+                _ => {
+                    let _ = writeln!(
+                        self.output,
+                        "Unknown command '{}'. Type 'help' for a list.",
+                        name
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Console;
+    use crate::{
+        ast::parser::Parser, capture::Capture, interpreter::Interpreter, lexer::scanner::Scanner,
+    };
+
+    const SCRIPT: &str = "var a = 1;\n{\n    var b = a + 1;\n    print b;\n}\nprint a;\n";
+
+    /// Runs `source` under a console fed `commands`, returning the exit status
+    /// and everything the script and the debugger wrote, in order.
+    fn debug(source: &str, commands: &str) -> (Option<i32>, String) {
+        let (tokens, _) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        let (statements, _) = Parser::new(tokens).parse_with_errors();
+
+        let output = Capture::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(output.clone());
+        interpreter.debugger = Some(Box::new(Console::new(
+            source,
+            Box::new(std::io::Cursor::new(commands.to_string())),
+            Box::new(output.clone()),
+            false,
+        )));
+
+        let status = interpreter.interpret(&statements);
+        (status, output.contents())
+    }
+
+    #[test]
+    fn break_print_and_continue() {
+        let (status, output) = debug(SCRIPT, "b 4\nc\np b * 10\nc\n");
+        assert_eq!(status, None);
+        assert_eq!(
+            output,
+            "[line 1] var a = 1;\n\
+             Breakpoint set at line 4.\n\
+             Breakpoint at line 4.\n\
+             [line 4] print b;\n\
+             20\n\
+             2\n\
+             1\n"
+        );
+    }
+
+    #[test]
+    fn step_walks_each_statement() {
+        let (_, output) = debug(SCRIPT, "s\ns\ns\nw\ns\ns\n");
+        assert_eq!(
+            output,
+            "[line 1] var a = 1;\n\
+             [line 2] {\n\
+             [line 3] var b = a + 1;\n\
+             [line 4] print b;\n\
+             [line 4] print b;\n\
+             2\n\
+             [line 6] print a;\n\
+             1\n"
+        );
+    }
+
+    #[test]
+    fn env_lists_each_scope() {
+        let (_, output) = debug(SCRIPT, "b 4\nc\ne\n");
+        assert!(
+            output.contains("block 1:\n  b = 2\nglobal:\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("  a = 1\n"), "{}", output);
+    }
+
+    #[test]
+    fn clear_and_breakpoints_manage_the_list() {
+        let (_, output) = debug(SCRIPT, "b 4\nb 6\nclear 4\nclear 4\nbreakpoints\nc\n");
+        assert_eq!(
+            output,
+            "[line 1] var a = 1;\n\
+             Breakpoint set at line 4.\n\
+             Breakpoint set at line 6.\n\
+             Breakpoint cleared at line 4.\n\
+             No breakpoint at line 4.\n\
+             line 6\n\
+             2\n\
+             Breakpoint at line 6.\n\
+             [line 6] print a;\n\
+             1\n"
+        );
+    }
+
+    #[test]
+    fn bad_commands_are_reported() {
+        let (_, output) = debug(SCRIPT, "b x\nfrobnicate\np nope\n");
+        assert_eq!(
+            output,
+            "[line 1] var a = 1;\n\
+             Expected a line number but got 'x'.\n\
+             Unknown command 'frobnicate'. Type 'help' for a list.\n\
+             Error: Undefined variable 'nope'.\n\
+             2\n\
+             1\n"
+        );
+    }
+
+    #[test]
+    fn quit_stops_the_script() {
+        let (status, output) = debug(SCRIPT, "q\n");
+        assert_eq!(status, Some(0));
+        assert_eq!(output, "[line 1] var a = 1;\n");
+    }
+}
@@ -0,0 +1,779 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    io::{self, BufRead, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use serde_json::{json, Value as Json};
+
+use crate::{
+    ast::{parser::Parser, statement::Stmt},
+    debugger::{self, Hook, Resume, Stepper, Stop},
+    error::{self, RuntimeError},
+    interpreter::Interpreter,
+    lexer::scanner::Scanner,
+    protocol,
+    value::Value,
+};
+
+const THREAD_ID: i64 = 1;
+const FRAME_ID: i64 = 1;
+
+/// Serves the Debug Adapter Protocol over `reader` and `writer`. The program
+/// from the `launch` request runs in `interpreter` once the client sends
+/// `configurationDone`. Returns 0 when the client disconnects and 1 if the
+/// input ends first.
+pub fn run(
+    reader: impl BufRead + 'static,
+    writer: impl Write + 'static,
+    interpreter: Interpreter,
+) -> i32 {
+    let connection = Rc::new(RefCell::new(Connection {
+        reader: Box::new(reader),
+        writer: Box::new(writer),
+        seq: 0,
+        disconnected: false,
+    }));
+
+    let mut interpreter = Some(interpreter);
+    let mut launch = None;
+    let mut breakpoints = BTreeSet::new();
+
+    loop {
+        let request = match connection.borrow_mut().read() {
+            Ok(Some(request)) => request,
+            Ok(None) => return 1,
+            Err(err) => {
+                eprintln!("dap: {}", err);
+                return 1;
+            }
+        };
+
+        let mut conn = connection.borrow_mut();
+        let result = match request["command"].as_str().unwrap_or("") {
+            "initialize" => conn
+                .respond(&request, capabilities())
+                .and_then(|_| conn.event("initialized", json!({}))),
+            "launch" => match Launch::from_arguments(&request["arguments"]) {
+                Ok(arguments) => {
+                    launch = Some(arguments);
+                    conn.respond(&request, json!({}))
+                }
+                Err(message) => conn.fail(&request, &message),
+            },
+            "setBreakpoints" => {
+                breakpoints = breakpoint_lines(&request["arguments"]);
+                conn.respond(&request, breakpoints_body(&breakpoints))
+            }
+            "threads" => conn.respond(&request, threads()),
+            "configurationDone" => match (launch.take(), interpreter.take()) {
+                (Some(launch), Some(interpreter)) => {
+                    let result = conn.respond(&request, json!({}));
+                    drop(conn);
+                    let breakpoints = std::mem::take(&mut breakpoints);
+                    run_program(&connection, launch, breakpoints, interpreter);
+
+                    if connection.borrow().disconnected {
+                        return 0;
+                    }
+                    result
+                }
+                _ => conn.fail(&request, "Expected a launch request first."),
+            },
+            "disconnect" => {
+                let _ = conn.respond(&request, json!({}));
+                return 0;
+            }
+            command => conn.fail(&request, &format!("Unsupported request '{}'.", command)),
+        };
+
+        if let Err(err) = result {
+            eprintln!("dap: {}", err);
+            return 1;
+        }
+    }
+}
+
+struct Connection {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+    seq: i64,
+    disconnected: bool,
+}
+
+impl Connection {
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        protocol::read_message(&mut self.reader)
+    }
+
+    fn send(&mut self, mut message: Json) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        protocol::write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Json, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn output(&mut self, category: &str, text: &str) -> io::Result<()> {
+        self.event("output", json!({ "category": category, "output": text }))
+    }
+}
+
+struct Launch {
+    program: String,
+    args: Vec<String>,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    fn from_arguments(arguments: &Json) -> Result<Self, String> {
+        let program = arguments["program"]
+            .as_str()
+            .ok_or_else(|| String::from("Missing 'program' in launch arguments."))?;
+
+        let args = arguments["args"]
+            .as_array()
+            .map(|args| {
+                args.iter()
+                    .filter_map(|arg| arg.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            program: String::from(program),
+            args,
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+fn capabilities() -> Json {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsEvaluateForHovers": true,
+    })
+}
+
+fn threads() -> Json {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
+
+fn breakpoint_lines(arguments: &Json) -> BTreeSet<usize> {
+    arguments["breakpoints"]
+        .as_array()
+        .map(|breakpoints| {
+            breakpoints
+                .iter()
+                .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                .map(|line| line as usize)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn breakpoints_body(lines: &BTreeSet<usize>) -> Json {
+    let breakpoints: Vec<Json> = lines
+        .iter()
+        .map(|line| json!({ "verified": true, "line": line }))
+        .collect();
+
+    json!({ "breakpoints": breakpoints })
+}
+
+/// Runs the launched program to completion, reporting its output, errors and
+/// exit status as events.
+fn run_program(
+    connection: &Rc<RefCell<Connection>>,
+    launch: Launch,
+    breakpoints: BTreeSet<usize>,
+    mut interpreter: Interpreter,
+) {
+    let code = match std::fs::read_to_string(&launch.program) {
+        Ok(source) => {
+            let mut stepper = Stepper::new(launch.stop_on_entry);
+            stepper.breakpoints = breakpoints;
+
+            interpreter.script_path = Some(launch.program.clone());
+            interpreter.script_args = launch.args;
+            interpreter.output = Box::new(OutputEvents::new(connection, "stdout"));
+            interpreter.debugger = Some(Box::new(Adapter {
+                connection: connection.clone(),
+                stepper,
+                program: launch.program,
+                handles: Vec::new(),
+            }));
+
+            run_source(connection, source, &mut interpreter)
+        }
+        Err(err) => {
+            let message = format!("Failed to read file: {}\n", err);
+            let _ = connection.borrow_mut().output("stderr", &message);
+            1
+        }
+    };
+
+    // Drop the output sink first so buffered text goes out before `exited`.
+    drop(interpreter);
+
+    let mut conn = connection.borrow_mut();
+    if !conn.disconnected {
+        let _ = conn.event("exited", json!({ "exitCode": code }));
+        let _ = conn.event("terminated", json!({}));
+    }
+}
+
+/// Runs `source` and returns the process status a plain run would exit with.
+/// Errors are reported as they would be on the command line, but reach the
+/// client as `stderr` output.
+fn run_source(
+    connection: &Rc<RefCell<Connection>>,
+    source: String,
+    interpreter: &mut Interpreter,
+) -> i32 {
+    let previous = error::set_reporter(Box::new(OutputEvents::new(connection, "stderr")));
+    error::reset_runtime_error();
+
+    let (tokens, scan_errors) = Scanner::new(source).scan_tokens_with_errors();
+    let (statements, parse_errors) = Parser::new(tokens).parse_with_errors();
+
+    let code = if error::report_syntax_errors(&scan_errors, &parse_errors) {
+        65
+    } else {
+        match interpreter.interpret(&statements) {
+            Some(code) => code,
+            None if error::had_runtime_error() => 70,
+            None => 0,
+        }
+    };
+
+    error::set_reporter(previous);
+    code
+}
+
+/// Sends what is written to it as `output` events of one category, a line at
+/// a time.
+struct OutputEvents {
+    connection: Rc<RefCell<Connection>>,
+    category: &'static str,
+    buffer: Vec<u8>,
+}
+
+impl OutputEvents {
+    fn new(connection: &Rc<RefCell<Connection>>, category: &'static str) -> Self {
+        Self {
+            connection: connection.clone(),
+            category,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        if let Some(end) = self.buffer.iter().rposition(|&byte| byte == b'\n') {
+            let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.connection
+                .borrow_mut()
+                .output(self.category, &String::from_utf8_lossy(&lines))?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let text = String::from_utf8_lossy(&self.buffer).into_owned();
+            self.buffer.clear();
+            self.connection.borrow_mut().output(self.category, &text)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for OutputEvents {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// What a `variablesReference` handed to the client points at. Handles are
+/// only valid while the program stays paused.
+enum Handle {
+    Scope(usize),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+}
+
+struct Adapter {
+    connection: Rc<RefCell<Connection>>,
+    stepper: Stepper,
+    program: String,
+    handles: Vec<Handle>,
+}
+
+impl Adapter {
+    fn stack_trace(&self, stmt: &Stmt) -> Json {
+        let token = stmt.first_token();
+        let name = Path::new(&self.program)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.program.clone());
+
+        json!({
+            "stackFrames": [{
+                "id": FRAME_ID,
+                "name": "<script>",
+                "source": { "name": name, "path": self.program },
+                "line": token.line,
+                "column": token.column,
+            }],
+            "totalFrames": 1,
+        })
+    }
+
+    fn scopes(&mut self, interpreter: &Interpreter) -> Json {
+        let count = debugger::scopes(&interpreter.environment).len();
+
+        let scopes: Vec<Json> = (0..count)
+            .map(|index| {
+                let name = match count - index - 1 {
+                    0 => String::from("Globals"),
+                    level => format!("Block {}", level),
+                };
+                json!({
+                    "name": name,
+                    "variablesReference": self.handle(Handle::Scope(index)),
+                    "expensive": false,
+                })
+            })
+            .collect();
+
+        json!({ "scopes": scopes })
+    }
+
+    fn variables(&mut self, interpreter: &Interpreter, reference: usize) -> Result<Json, String> {
+        let variables: Vec<(String, Value)> = match self.handles.get(reference.wrapping_sub(1)) {
+            Some(Handle::Scope(index)) => debugger::scopes(&interpreter.environment)
+                .into_iter()
+                .nth(*index)
+                .unwrap_or_default(),
+            Some(Handle::List(list)) => list
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (index.to_string(), value.clone()))
+                .collect(),
+            Some(Handle::Map(map)) => map
+                .borrow()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            None => return Err(format!("Unknown variables reference {}.", reference)),
+        };
+
+        let variables: Vec<Json> = variables
+            .into_iter()
+            .map(|(name, value)| {
+                let (value, reference) = self.describe(&value);
+                json!({ "name": name, "value": value, "variablesReference": reference })
+            })
+            .collect();
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// The text shown for `value`, and a reference to expand it if it is a list
+    /// or a map.
+    fn describe(&mut self, value: &Value) -> (String, usize) {
+        match value {
+            Value::String(string) => (format!("{:?}", string), 0),
+            Value::List(list) => (value.to_string(), self.handle(Handle::List(list.clone()))),
+            Value::Map(map) => (value.to_string(), self.handle(Handle::Map(map.clone()))),
+            value => (value.to_string(), 0),
+        }
+    }
+
+    fn handle(&mut self, handle: Handle) -> usize {
+        self.handles.push(handle);
+        self.handles.len()
+    }
+}
+
+impl Hook for Adapter {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), RuntimeError> {
+        let depth = interpreter.depth();
+        let reason = match self.stepper.should_stop(stmt.first_token().line, depth) {
+            Some(Stop::Entry) => "entry",
+            Some(Stop::Step) => "step",
+            Some(Stop::Breakpoint) => "breakpoint",
+            None => return Ok(()),
+        };
+
+        let _ = interpreter.output.flush();
+        let connection = self.connection.clone();
+        let stopped = json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true });
+        let mut result = connection.borrow_mut().event("stopped", stopped);
+        self.handles.clear();
+
+        loop {
+            let request = match result.and_then(|_| connection.borrow_mut().read()) {
+                Ok(Some(request)) => request,
+                _ => {
+                    connection.borrow_mut().disconnected = true;
+                    return Err(RuntimeError::exit(stmt.first_token().clone(), 1));
+                }
+            };
+
+            let arguments = &request["arguments"];
+            let resume = match request["command"].as_str().unwrap_or("") {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepIn),
+                "stepOut" => Some(Resume::StepOut),
+                _ => None,
+            };
+
+            if let Some(resume) = resume {
+                self.stepper.resume(resume, depth);
+                let body = json!({ "allThreadsContinued": true });
+                return match connection.borrow_mut().respond(&request, body) {
+                    Ok(()) => Ok(()),
+                    Err(_) => {
+                        connection.borrow_mut().disconnected = true;
+                        Err(RuntimeError::exit(stmt.first_token().clone(), 1))
+                    }
+                };
+            }
+
+            let body = match request["command"].as_str().unwrap_or("") {
+                "threads" => Ok(threads()),
+                "stackTrace" => Ok(self.stack_trace(stmt)),
+                "scopes" => Ok(self.scopes(interpreter)),
+                "variables" => {
+                    let reference = arguments["variablesReference"].as_u64().unwrap_or(0);
+                    self.variables(interpreter, reference as usize)
+                }
+                "evaluate" => {
+                    let expression = arguments["expression"].as_str().unwrap_or("");
+                    debugger::evaluate(interpreter, expression).map(|value| {
+                        let (result, reference) = self.describe(&value);
+                        json!({ "result": result, "variablesReference": reference })
+                    })
+                }
+                "setBreakpoints" => {
+                    self.stepper.breakpoints = breakpoint_lines(arguments);
+                    Ok(breakpoints_body(&self.stepper.breakpoints))
+                }
+                "disconnect" => {
+                    let mut conn = connection.borrow_mut();
+                    let _ = conn.respond(&request, json!({}));
+                    conn.disconnected = true;
+                    return Err(RuntimeError::exit(stmt.first_token().clone(), 0));
+                }
+                command => Err(format!("Unsupported request '{}'.", command)),
+            };
+
+            let mut conn = connection.borrow_mut();
+            result = match body {
+                Ok(body) => conn.respond(&request, body),
+                Err(message) => conn.fail(&request, &message),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::Capture;
+
+    const SCRIPT: &str =
+        "var a = 1;\nvar xs = split(\",\", \"x,y\");\n{\n    var b = a + 1;\n    print b;\n}\nprint a;\n";
+
+    /// Writes `source` to a fresh file and returns its path.
+    fn program(name: &str, source: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("rslox-dap-{}-{}.lox", std::process::id(), name));
+        std::fs::write(&path, source).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Feeds `requests` to the adapter, numbering them from 1, and returns its
+    /// exit status and everything it sent.
+    fn transcript(requests: &[Json]) -> (i32, Vec<Json>) {
+        let mut input = Vec::new();
+        for (seq, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+            request["seq"] = json!(seq + 1);
+            request["type"] = json!("request");
+            protocol::write_message(&mut input, &request).unwrap();
+        }
+
+        let output = Capture::new();
+        let status = run(io::Cursor::new(input), output.clone(), Interpreter::new());
+
+        let mut reader = io::Cursor::new(output.contents().into_bytes());
+        let mut sent = Vec::new();
+        while let Some(message) = protocol::read_message(&mut reader).unwrap() {
+            sent.push(message);
+        }
+
+        (status, sent)
+    }
+
+    fn request(command: &str, arguments: Json) -> Json {
+        json!({ "command": command, "arguments": arguments })
+    }
+
+    /// A compact form of each message: `response command` with its body, or
+    /// `event name` with its body.
+    fn summary(message: &Json) -> (String, Json) {
+        match message["type"].as_str() {
+            Some("response") if message["success"] == true => (
+                format!("response {}", message["command"].as_str().unwrap()),
+                message["body"].clone(),
+            ),
+            Some("response") => (
+                format!("failed {}", message["command"].as_str().unwrap()),
+                message["message"].clone(),
+            ),
+            _ => (
+                format!("event {}", message["event"].as_str().unwrap()),
+                message["body"].clone(),
+            ),
+        }
+    }
+
+    #[test]
+    fn breakpoint_session() {
+        let path = program("session", SCRIPT);
+        let (status, sent) = transcript(&[
+            request("initialize", json!({ "adapterID": "rslox" })),
+            request("launch", json!({ "program": path })),
+            request("setBreakpoints", json!({ "breakpoints": [{ "line": 5 }] })),
+            request("configurationDone", json!({})),
+            request("stackTrace", json!({ "threadId": THREAD_ID })),
+            request("scopes", json!({ "frameId": FRAME_ID })),
+            request("variables", json!({ "variablesReference": 1 })),
+            request("variables", json!({ "variablesReference": 2 })),
+            request("variables", json!({ "variablesReference": 3 })),
+            request("continue", json!({ "threadId": THREAD_ID })),
+            request("disconnect", json!({})),
+        ]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(status, 0);
+        let name = Path::new(&path).file_name().unwrap().to_str().unwrap();
+        let summaries: Vec<_> = sent.iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            [
+                (String::from("response initialize"), capabilities()),
+                (String::from("event initialized"), json!({})),
+                (String::from("response launch"), json!({})),
+                (
+                    String::from("response setBreakpoints"),
+                    json!({ "breakpoints": [{ "verified": true, "line": 5 }] }),
+                ),
+                (String::from("response configurationDone"), json!({})),
+                (
+                    String::from("event stopped"),
+                    json!({ "reason": "breakpoint", "threadId": 1, "allThreadsStopped": true }),
+                ),
+                (
+                    String::from("response stackTrace"),
+                    json!({
+                        "stackFrames": [{
+                            "id": 1,
+                            "name": "<script>",
+                            "source": { "name": name, "path": path },
+                            "line": 5,
+                            "column": 5,
+                        }],
+                        "totalFrames": 1,
+                    }),
+                ),
+                (
+                    String::from("response scopes"),
+                    json!({ "scopes": [
+                        { "name": "Block 1", "variablesReference": 1, "expensive": false },
+                        { "name": "Globals", "variablesReference": 2, "expensive": false },
+                    ] }),
+                ),
+                (
+                    String::from("response variables"),
+                    json!({ "variables": [
+                        { "name": "b", "value": "2", "variablesReference": 0 },
+                    ] }),
+                ),
+                (
+                    String::from("response variables"),
+                    json!({ "variables": [
+                        { "name": "a", "value": "1", "variablesReference": 0 },
+                        { "name": "xs", "value": "[x, y]", "variablesReference": 3 },
+                    ] }),
+                ),
+                (
+                    String::from("response variables"),
+                    json!({ "variables": [
+                        { "name": "0", "value": "\"x\"", "variablesReference": 0 },
+                        { "name": "1", "value": "\"y\"", "variablesReference": 0 },
+                    ] }),
+                ),
+                (
+                    String::from("response continue"),
+                    json!({ "allThreadsContinued": true }),
+                ),
+                (
+                    String::from("event output"),
+                    json!({ "category": "stdout", "output": "2\n" }),
+                ),
+                (
+                    String::from("event output"),
+                    json!({ "category": "stdout", "output": "1\n" }),
+                ),
+                (String::from("event exited"), json!({ "exitCode": 0 })),
+                (String::from("event terminated"), json!({})),
+                (String::from("response disconnect"), json!({})),
+            ]
+        );
+
+        let seqs: Vec<_> = sent.iter().map(|message| message["seq"].clone()).collect();
+        assert_eq!(
+            seqs,
+            (1..=sent.len()).map(|seq| json!(seq)).collect::<Vec<_>>()
+        );
+        assert_eq!(sent[6]["request_seq"], 5);
+    }
+
+    #[test]
+    fn disconnect_while_paused_stops_the_program() {
+        let path = program("disconnect", SCRIPT);
+        let (status, sent) = transcript(&[
+            request("initialize", json!({})),
+            request("launch", json!({ "program": path, "stopOnEntry": true })),
+            request("configurationDone", json!({})),
+            request("disconnect", json!({})),
+        ]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(status, 0);
+        let summaries: Vec<_> = sent.iter().map(|message| summary(message).0).collect();
+        assert_eq!(
+            summaries,
+            [
+                "response initialize",
+                "event initialized",
+                "response launch",
+                "response configurationDone",
+                "event stopped",
+                "response disconnect",
+            ]
+        );
+        assert_eq!(sent[4]["body"]["reason"], "entry");
+    }
+
+    #[test]
+    fn bad_requests_fail() {
+        let (status, sent) = transcript(&[
+            request("launch", json!({})),
+            request("configurationDone", json!({})),
+            request("frobnicate", json!({})),
+        ]);
+
+        assert_eq!(status, 1);
+        let summaries: Vec<_> = sent.iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            [
+                (
+                    String::from("failed launch"),
+                    json!("Missing 'program' in launch arguments."),
+                ),
+                (
+                    String::from("failed configurationDone"),
+                    json!("Expected a launch request first."),
+                ),
+                (
+                    String::from("failed frobnicate"),
+                    json!("Unsupported request 'frobnicate'."),
+                ),
+            ]
+        );
+    }
+
+    /// Runs `source` without stopping and returns the output events and the
+    /// exit code the adapter reported.
+    fn run_to_exit(name: &str, source: &str) -> (Vec<Json>, Json) {
+        let path = program(name, source);
+        let (_, sent) = transcript(&[
+            request("launch", json!({ "program": path })),
+            request("configurationDone", json!({})),
+            request("disconnect", json!({})),
+        ]);
+        std::fs::remove_file(&path).unwrap();
+
+        let events = |name: &str| {
+            sent.iter()
+                .filter(|message| message["event"] == name)
+                .map(|message| message["body"].clone())
+                .collect::<Vec<_>>()
+        };
+        (events("output"), events("exited")[0]["exitCode"].clone())
+    }
+
+    #[test]
+    fn errors_are_reported_as_on_the_command_line() {
+        let (output, code) = run_to_exit("runtime", "print 1;\nprint -nil;\nprint 3;\n");
+        assert_eq!(
+            output,
+            [
+                json!({ "category": "stdout", "output": "1\n" }),
+                json!({ "category": "stderr", "output": "[line 2] Error at '-': Operand must be a number.\n" }),
+                json!({ "category": "stdout", "output": "3\n" }),
+            ]
+        );
+        assert_eq!(code, 70);
+
+        let (output, code) = run_to_exit("syntax", "print 1;\nprint (;\n");
+        assert_eq!(
+            output,
+            [
+                json!({ "category": "stderr", "output": "[line 2] Error at ';': Expected expression.\n" })
+            ]
+        );
+        assert_eq!(code, 65);
+    }
+}
@@ -0,0 +1,140 @@
+pub mod console;
+pub mod dap;
+
+use std::collections::BTreeSet;
+
+use crate::{
+    ast::{parser::Parser, statement::Stmt},
+    environment::Environment,
+    error::RuntimeError,
+    interpreter::Interpreter,
+    lexer::scanner::Scanner,
+    value::Value,
+};
+
+/// Called by the interpreter before each statement it executes. Returning an
+/// error stops the script as if the statement had raised it.
+pub trait Hook {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), RuntimeError>;
+}
+
+/// Why execution paused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    Entry,
+    Step,
+    Breakpoint,
+}
+
+/// How to carry on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Entry,
+    Run,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+/// Breakpoints and stepping state shared by the debugger front ends. Depth is
+/// the interpreter's statement nesting depth, so stepping over an `if` or a
+/// block runs everything inside it.
+pub struct Stepper {
+    pub breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    previous_line: usize,
+}
+
+impl Stepper {
+    pub fn new(stop_on_entry: bool) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            mode: if stop_on_entry {
+                Mode::Entry
+            } else {
+                Mode::Run
+            },
+            previous_line: 0,
+        }
+    }
+
+    /// Decides whether to pause before a statement starting on `line`. A
+    /// breakpoint only fires on the first statement of its line.
+    pub fn should_stop(&mut self, line: usize, depth: usize) -> Option<Stop> {
+        let new_line = line != self.previous_line;
+        self.previous_line = line;
+
+        let stop = match self.mode {
+            Mode::Entry => Some(Stop::Entry),
+            Mode::Run => None,
+            Mode::StepIn => Some(Stop::Step),
+            Mode::StepOver(from) if depth <= from => Some(Stop::Step),
+            Mode::StepOut(from) if depth < from => Some(Stop::Step),
+            _ => None,
+        };
+
+        stop.or_else(|| (new_line && self.breakpoints.contains(&line)).then_some(Stop::Breakpoint))
+    }
+
+    /// Resumes from a pause at `depth`.
+    pub fn resume(&mut self, resume: Resume, depth: usize) {
+        self.mode = match resume {
+            Resume::Continue => Mode::Run,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+        };
+    }
+}
+
+/// The scopes visible from `environment`, innermost first, each with its
+/// variables sorted by name. Native and host functions are left out of the
+/// globals.
+pub fn scopes(environment: &Environment) -> Vec<Vec<(String, Value)>> {
+    let mut scopes = Vec::new();
+    let mut scope = Some(environment);
+
+    while let Some(environment) = scope {
+        let mut variables: Vec<(String, Value)> = environment
+            .values()
+            .iter()
+            .filter(|(_, value)| {
+                !matches!(value, Value::NativeFunction(_) | Value::HostFunction(_))
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        scopes.push(variables);
+        scope = environment.enclosing();
+    }
+
+    scopes
+}
+
+/// Evaluates `source` as an expression in the paused scope. Errors come back
+/// as messages so reporting them does not count as a runtime error.
+pub fn evaluate(interpreter: &mut Interpreter, source: &str) -> Result<Value, String> {
+    let (tokens, errors) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+    if let Some(err) = errors.first() {
+        return Err(err.message.clone());
+    }
+
+    let expr = Parser::new(tokens)
+        .parse_expression()
+        .map_err(|err| err.message)?;
+
+    interpreter.evaluate(&expr).map_err(|err| err.message)
+}
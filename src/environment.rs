@@ -22,6 +22,44 @@ impl Environment {
         }
     }
 
+    /// The variables defined directly in this scope.
+    pub fn values(&self) -> &HashMap<String, Value> {
+        &self.values
+    }
+
+    pub fn enclosing(&self) -> Option<&Environment> {
+        self.enclosing.as_deref()
+    }
+
+    /// The value of `name` in this scope or an enclosing one.
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value),
+            None => self.enclosing.as_ref()?.lookup(name),
+        }
+    }
+
+    /// A copy of this scope and the enclosing ones whose lists and maps are
+    /// copies too, so nothing done through it shows up here. Variables that
+    /// share a list or map still share one in the copy.
+    pub fn deep_copy(&self) -> Environment {
+        self.deep_copy_with(&mut HashMap::new())
+    }
+
+    fn deep_copy_with(&self, copies: &mut HashMap<*const (), Value>) -> Environment {
+        Environment {
+            values: self
+                .values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.deep_copy(copies)))
+                .collect(),
+            enclosing: self
+                .enclosing
+                .as_ref()
+                .map(|enclosing| Box::new(enclosing.deep_copy_with(copies))),
+        }
+    }
+
     pub fn define(&mut self, name: String, value: Value) {
         self.values.insert(name, value);
     }
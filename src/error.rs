@@ -1,27 +1,91 @@
-use crate::lexer::{token::Token, token_type::TokenType};
+use std::{
+    cell::{Cell, RefCell},
+    io::Write,
+};
 
-static mut HAD_ERROR: bool = false;
-static mut HAD_RUNTIME_ERROR: bool = false;
+use crate::{
+    lexer::{token::Token, token_type::TokenType},
+    limits::Limit,
+};
+
+// Like the reporter, the flags belong to the thread doing the reporting, so
+// interpreters on different threads do not see each other's errors.
+thread_local! {
+    static HAD_ERROR: Cell<bool> = const { Cell::new(false) };
+    static HAD_RUNTIME_ERROR: Cell<bool> = const { Cell::new(false) };
+    static REPORTER: RefCell<Box<dyn Write>> = RefCell::new(Box::new(std::io::stderr()));
+}
 
 #[derive(Debug, Clone)]
 pub struct ParseError;
 
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub token: Token,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Error,
+    /// Not a failure: unwinds the script when it calls `exit(code)`.
+    Exit(i32),
+    /// The script ran past one of the interpreter's `Limits`.
+    Limit(Limit),
+    /// The host asked the script to stop through an `InterruptHandle`.
+    Interrupted,
+}
+
 #[derive(Debug)]
 pub struct RuntimeError {
     pub token: Token,
     pub message: String,
+    pub kind: ErrorKind,
 }
 
 impl RuntimeError {
     pub fn new(token: Token, message: String) -> RuntimeError {
-        RuntimeError { token, message }
+        RuntimeError {
+            token,
+            message,
+            kind: ErrorKind::Error,
+        }
+    }
+
+    pub fn exit(token: Token, code: i32) -> RuntimeError {
+        RuntimeError {
+            token,
+            message: format!("Exited with status {}.", code),
+            kind: ErrorKind::Exit(code),
+        }
+    }
+
+    pub fn interrupted(token: Token) -> RuntimeError {
+        RuntimeError {
+            token,
+            message: String::from("Interrupted."),
+            kind: ErrorKind::Interrupted,
+        }
+    }
+
+    pub fn limit(token: Token, limit: Limit, message: String) -> RuntimeError {
+        RuntimeError {
+            token,
+            message,
+            kind: ErrorKind::Limit(limit),
+        }
     }
 }
 
 impl std::fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        unsafe { HAD_RUNTIME_ERROR = true };
-
         write!(f, "[line {}] Error", self.token.line)?;
         match &self.token.token_type {
             TokenType::Eof => write!(f, " at end")?,
@@ -33,30 +97,202 @@ impl std::fmt::Display for RuntimeError {
 
 impl std::error::Error for RuntimeError {}
 
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.message)
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[line {}] Error", self.token.line)?;
+        match &self.token.token_type {
+            TokenType::Eof => write!(f, " at end")?,
+            _ => write!(f, " at '{}'", self.token.lexeme)?,
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Everything that can go wrong running code through the embedding API.
+#[derive(Debug)]
+pub enum LoxError {
+    /// The source could not be read.
+    Io(std::io::Error),
+    Scan(Vec<ScanError>),
+    Parse(Vec<SyntaxError>),
+    /// Raised while running, including a script calling `exit`.
+    Runtime(RuntimeError),
+}
+
+impl std::fmt::Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LoxError::Io(err) => write!(f, "Failed to read file: {}", err),
+            LoxError::Scan(errors) => write_all(f, errors),
+            LoxError::Parse(errors) => write_all(f, errors),
+            LoxError::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+fn write_all<T: std::fmt::Display>(f: &mut std::fmt::Formatter, errors: &[T]) -> std::fmt::Result {
+    for (i, err) in errors.iter().enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        write!(f, "{}", err)?;
+    }
+    Ok(())
+}
+
+impl std::error::Error for LoxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoxError::Io(err) => Some(err),
+            LoxError::Runtime(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LoxError {
+    fn from(err: std::io::Error) -> Self {
+        LoxError::Io(err)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(err: RuntimeError) -> Self {
+        LoxError::Runtime(err)
+    }
+}
+
 pub fn error(line: usize, message: &str) {
-    report(line, "", message);
+    report(&ScanError {
+        line,
+        column: 0,
+        message: String::from(message),
+    });
 }
 
 pub fn error_token(token: &Token, message: &str) {
-    match token.token_type {
-        TokenType::Eof => report(token.line, "at end", message),
-        _ => report(token.line, &format!("at '{}'", token.lexeme), message),
+    report(&SyntaxError {
+        token: token.clone(),
+        message: String::from(message),
+    });
+}
+
+/// Reports the errors collected by `scan_tokens_with_errors` and
+/// `parse_with_errors`, returning whether there were any.
+pub fn report_syntax_errors(scan_errors: &[ScanError], parse_errors: &[SyntaxError]) -> bool {
+    for err in scan_errors {
+        report(err);
+    }
+    for err in parse_errors {
+        report(err);
     }
+
+    !scan_errors.is_empty() || !parse_errors.is_empty()
 }
 
 pub fn had_error() -> bool {
-    unsafe { HAD_ERROR }
+    HAD_ERROR.get()
 }
 
 pub fn had_runtime_error() -> bool {
-    unsafe { HAD_RUNTIME_ERROR }
+    HAD_RUNTIME_ERROR.get()
+}
+
+/// Reports an error that stopped a script.
+pub fn runtime_error(err: &RuntimeError) {
+    HAD_RUNTIME_ERROR.set(true);
+    emit(format_args!("{}", err));
 }
 
 pub fn reset_error() {
-    unsafe { HAD_ERROR = false };
+    HAD_ERROR.set(false);
+}
+
+pub fn reset_runtime_error() {
+    HAD_RUNTIME_ERROR.set(false);
+}
+
+/// Scan and parse errors are written with their `Display` impls, so reported
+/// and returned errors read the same.
+fn report(err: &dyn std::fmt::Display) {
+    HAD_ERROR.set(true);
+    emit(format_args!("{}", err));
+}
+
+/// Sends the scan, parse and runtime errors reported on this thread to
+/// `reporter` instead of stderr, returning the previous reporter.
+pub fn set_reporter(reporter: Box<dyn Write>) -> Box<dyn Write> {
+    REPORTER.with(|current| current.replace(reporter))
+}
+
+fn emit(report: std::fmt::Arguments) {
+    REPORTER.with(|reporter| {
+        // There is nowhere left to report a failure to write a report.
+        let _ = writeln!(reporter.borrow_mut(), "{}", report);
+    });
 }
 
-fn report(line: usize, location: &str, message: &str) {
-    unsafe { HAD_ERROR = true };
-    println!("[line {}] Error {}: {}", line, location, message);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::parser::Parser, capture::Capture, interpreter::Interpreter, lexer::scanner::Scanner,
+    };
+
+    #[test]
+    fn the_reporter_captures_scan_parse_and_runtime_errors() {
+        let reports = Capture::new();
+        let previous = set_reporter(Box::new(reports.clone()));
+
+        error(3, "Unexpected character.");
+        error_token(
+            &Token::new(TokenType::Eof, String::new(), None, 4, 1),
+            "Expected ';' after value.",
+        );
+        runtime_error(&RuntimeError::new(
+            Token::new(TokenType::Minus, String::from("-"), None, 5, 7),
+            String::from("Operand must be a number."),
+        ));
+        let flags = (had_error(), had_runtime_error());
+
+        set_reporter(previous);
+        reset_error();
+
+        assert_eq!(
+            reports.contents(),
+            "[line 3] Error: Unexpected character.\n\
+             [line 4] Error at end: Expected ';' after value.\n\
+             [line 5] Error at '-': Operand must be a number.\n"
+        );
+        assert_eq!(flags, (true, true));
+    }
+
+    #[test]
+    fn print_output_and_errors_go_to_separate_sinks() {
+        let output = Capture::new();
+        let reports = Capture::new();
+        let previous = set_reporter(Box::new(reports.clone()));
+
+        let (tokens, _) = Scanner::new(String::from(
+            "print \"before\";\nprint nil + 1;\nprint \"after\";",
+        ))
+        .scan_tokens_with_errors();
+        let statements = Parser::new(tokens).parse();
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(output.clone());
+        interpreter.interpret(&statements);
+
+        set_reporter(previous);
+        assert_eq!(output.contents(), "before\nafter\n");
+        assert_eq!(
+            reports.contents(),
+            "[line 2] Error at '+': Operands must be two numbers or two strings.\n"
+        );
+    }
 }
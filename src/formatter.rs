@@ -0,0 +1,285 @@
+use crate::{
+    ast::parser::Parser,
+    error,
+    lexer::{scanner::Scanner, token::Token, token_type::TokenType},
+};
+
+const INDENT: &str = "    ";
+
+/// Rewrites `source` in the canonical style: four-space indentation, one
+/// statement per line, spaces around binary operators and opening braces on the
+/// line of the statement that owns them. Comments are kept where they were and a
+/// run of blank lines is collapsed into one. Returns `None` after reporting the
+/// errors if `source` does not parse.
+pub fn format(source: String) -> Option<String> {
+    let (tokens, scan_errors) = Scanner::with_comments(source).scan_tokens_with_errors();
+
+    let code = tokens
+        .iter()
+        .filter(|token| token.token_type != TokenType::Comment)
+        .cloned()
+        .collect();
+    let (_, parse_errors) = Parser::new(code).parse_with_errors();
+
+    if error::report_syntax_errors(&scan_errors, &parse_errors) {
+        return None;
+    }
+
+    let mut formatter = Formatter::new();
+    for token in &tokens {
+        formatter.token(token);
+    }
+
+    Some(formatter.finish())
+}
+
+struct Formatter {
+    output: String,
+    indent: usize,
+    paren_depth: usize,
+    at_line_start: bool,
+    newline_pending: bool,
+    in_statement: bool,
+    previous: Option<Token>,
+    previous_unary: bool,
+    previous_end_line: usize,
+}
+
+impl Formatter {
+    fn new() -> Self {
+        Self {
+            output: String::new(),
+            indent: 0,
+            paren_depth: 0,
+            at_line_start: true,
+            newline_pending: false,
+            in_statement: false,
+            previous: None,
+            previous_unary: false,
+            previous_end_line: 0,
+        }
+    }
+
+    fn token(&mut self, token: &Token) {
+        if token.token_type == TokenType::Eof {
+            return;
+        }
+
+        if self.newline_pending {
+            if self.is_trailing_comment(token) {
+                self.output.push(' ');
+                self.output.push_str(token.lexeme.trim_end());
+                self.remember(token, false);
+                return;
+            }
+
+            if token.token_type == TokenType::Else && self.previous_is(TokenType::RightBrace) {
+                self.newline_pending = false;
+            } else {
+                self.newline(token);
+            }
+        }
+
+        if token.token_type == TokenType::RightBrace {
+            self.indent = self.indent.saturating_sub(1);
+        }
+
+        let unary = self.is_unary(token);
+
+        if self.at_line_start {
+            // Statements broken across lines by a comment get a continuation indent.
+            let continuation = self.in_statement && token.token_type != TokenType::Comment;
+            self.output
+                .push_str(&INDENT.repeat(self.indent + continuation as usize));
+        } else if self.needs_space(token) {
+            self.output.push(' ');
+        }
+
+        self.output.push_str(token.lexeme.trim_end());
+        self.at_line_start = false;
+
+        match token.token_type {
+            TokenType::LeftBrace => {
+                self.indent += 1;
+                self.newline_pending = true;
+            }
+            TokenType::RightBrace => self.newline_pending = true,
+            TokenType::LeftParen => self.paren_depth += 1,
+            TokenType::RightParen => self.paren_depth = self.paren_depth.saturating_sub(1),
+            TokenType::Semicolon if self.paren_depth == 0 => self.newline_pending = true,
+            TokenType::Comment if token.lexeme.starts_with("//") || self.starts_line(token) => {
+                self.newline_pending = true
+            }
+            _ => {}
+        }
+
+        match token.token_type {
+            TokenType::Semicolon | TokenType::LeftBrace | TokenType::RightBrace => {
+                self.in_statement = false
+            }
+            TokenType::Comment => {}
+            _ => self.in_statement = true,
+        }
+
+        self.remember(token, unary);
+    }
+
+    fn finish(mut self) -> String {
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+
+        self.output
+    }
+
+    /// Starts a new line for `token`, keeping one blank line if the source had any
+    /// between it and the previous token.
+    fn newline(&mut self, token: &Token) {
+        self.output.push('\n');
+
+        let blank_line = token.line > self.previous_end_line + 1
+            && token.token_type != TokenType::RightBrace
+            && !self.previous_is(TokenType::LeftBrace);
+        if blank_line {
+            self.output.push('\n');
+        }
+
+        self.at_line_start = true;
+        self.newline_pending = false;
+    }
+
+    fn remember(&mut self, token: &Token, unary: bool) {
+        self.previous_end_line = token.line + token.lexeme.matches('\n').count();
+        self.previous = Some(token.clone());
+        self.previous_unary = unary;
+    }
+
+    fn is_trailing_comment(&self, token: &Token) -> bool {
+        token.token_type == TokenType::Comment
+            && self.previous.is_some()
+            && token.line == self.previous_end_line
+    }
+
+    fn starts_line(&self, token: &Token) -> bool {
+        self.previous.is_none() || token.line > self.previous_end_line
+    }
+
+    fn previous_is(&self, token_type: TokenType) -> bool {
+        matches!(&self.previous, Some(previous) if previous.token_type == token_type)
+    }
+
+    fn ends_operand(&self) -> bool {
+        matches!(
+            self.previous.as_ref().map(|token| &token.token_type),
+            Some(
+                TokenType::Identifier
+                    | TokenType::Number
+                    | TokenType::String
+                    | TokenType::RightParen
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+                    | TokenType::This
+            )
+        )
+    }
+
+    fn is_unary(&self, token: &Token) -> bool {
+        match token.token_type {
+            TokenType::Bang => true,
+            TokenType::Minus => !self.ends_operand(),
+            _ => false,
+        }
+    }
+
+    fn needs_space(&self, token: &Token) -> bool {
+        if self.previous_unary
+            || self.previous_is(TokenType::LeftParen)
+            || self.previous_is(TokenType::Dot)
+        {
+            return false;
+        }
+
+        match token.token_type {
+            TokenType::Semicolon | TokenType::Comma | TokenType::RightParen | TokenType::Dot => {
+                false
+            }
+            TokenType::LeftParen => {
+                !(self.previous_is(TokenType::Identifier)
+                    || self.previous_is(TokenType::RightParen))
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_str(source: &str) -> String {
+        format(String::from(source)).expect("source should parse")
+    }
+
+    /// Formats `source`, checks the result against `expected` and checks that
+    /// formatting it again changes nothing.
+    fn assert_formats(source: &str, expected: &str) {
+        let formatted = format_str(source);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_str(&formatted), formatted, "not idempotent");
+    }
+
+    #[test]
+    fn declarations_and_expressions() {
+        assert_formats(
+            "var a=1;var b=-a*(2+3)/4;print !true==false and b>=1 or nil;",
+            "var a = 1;\nvar b = -a * (2 + 3) / 4;\nprint !true == false and b >= 1 or nil;\n",
+        );
+    }
+
+    #[test]
+    fn blocks_and_control_flow() {
+        assert_formats(
+            "if(a<1){print a;}else{a=a-1;}\n{var b=0;{b=clock();}}",
+            "if (a < 1) {\n    print a;\n} else {\n    a = a - 1;\n}\n{\n    var b = 0;\n    {\n        b = clock();\n    }\n}\n",
+        );
+    }
+
+    #[test]
+    fn calls_and_tests() {
+        assert_formats(
+            "test \"adds\"{assertEqual(add(1,2),3);}",
+            "test \"adds\" {\n    assertEqual(add(1, 2), 3);\n}\n",
+        );
+    }
+
+    #[test]
+    fn comments_and_blank_lines() {
+        assert_formats(
+            "// header\nvar a = 1; // trailing\n\n\n/* block */\nprint a;",
+            "// header\nvar a = 1; // trailing\n\n/* block */\nprint a;\n",
+        );
+    }
+
+    #[test]
+    fn non_ascii_strings_and_comments() {
+        assert_formats(
+            "print \"héllo 😀\";// ça va\nvar s=\"日本\"+\"語\";",
+            "print \"héllo 😀\"; // ça va\nvar s = \"日本\" + \"語\";\n",
+        );
+    }
+
+    #[test]
+    fn already_formatted_source_is_unchanged() {
+        let source = "var a = 1;\nif (a) {\n    print a; // é\n}\n";
+        assert_eq!(format_str(source), source);
+    }
+
+    #[test]
+    fn invalid_source_is_not_formatted() {
+        let reporter = error::set_reporter(Box::new(std::io::sink()));
+        assert_eq!(format(String::from("print ;")), None);
+        assert_eq!(format(String::from("print \"open")), None);
+        error::set_reporter(reporter);
+    }
+}
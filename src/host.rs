@@ -0,0 +1,159 @@
+use std::io::Write;
+
+use crate::{error::RuntimeError, interpreter::Interpreter, lexer::token::Token, value::Value};
+
+pub type HostFn = dyn Fn(&mut Context, &[Value]) -> Result<Value, RuntimeError>;
+
+/// A function the embedding program registers under a global name. Unlike the
+/// built-in natives it can be a closure capturing host state; use `Cell` or
+/// `RefCell` for state the function changes.
+pub struct HostFunction {
+    pub name: String,
+    pub arity: Option<usize>,
+    pub function: Box<HostFn>,
+}
+
+impl std::fmt::Debug for HostFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl PartialEq for HostFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// What a host function can reach while it is being called.
+pub struct Context<'a> {
+    interpreter: &'a mut Interpreter,
+    paren: &'a Token,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(interpreter: &'a mut Interpreter, paren: &'a Token) -> Self {
+        Self { interpreter, paren }
+    }
+
+    /// The line of the call in the script.
+    pub fn line(&self) -> usize {
+        self.paren.line
+    }
+
+    /// An error reported at the call site, e.g. `[line 3] Error at ')': ...`.
+    pub fn error(&self, message: impl Into<String>) -> RuntimeError {
+        RuntimeError::new(self.paren.clone(), message.into())
+    }
+
+    /// Fails with `ErrorKind::Interrupted` if the script was asked to stop;
+    /// call it between iterations of long-running work.
+    pub fn check_interrupt(&self) -> Result<(), RuntimeError> {
+        self.interpreter.check_interrupt(self.paren)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter.environment.lookup(name).cloned()
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.interpreter
+            .environment
+            .define(String::from(name), value);
+    }
+
+    /// Where the script's `print` statements go.
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.interpreter.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{capture::Capture, error::ErrorKind, lox::Lox, value::Value};
+
+    #[test]
+    fn closures_capture_host_state() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = log.clone();
+
+        let mut lox = Lox::new();
+        lox.register_fn("record", None, move |_, args| {
+            sink.borrow_mut().push(args.len());
+            Ok(Value::Nil)
+        });
+
+        lox.eval("record(); record(1, 2); record(\"a\");").unwrap();
+        assert_eq!(*log.borrow(), [0, 2, 1]);
+    }
+
+    #[test]
+    fn a_fixed_arity_is_checked() {
+        let mut lox = Lox::new();
+        lox.register_fn("one", Some(1), |_, args| Ok(args[0].clone()));
+
+        assert_eq!(lox.eval("one(5)").unwrap(), Value::Number(5.0));
+        let err = lox.eval("one(1, 2)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[line 1] Error at ')': Expected 1 arguments but got 2."
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_call_site() {
+        let mut lox = Lox::new();
+        lox.register_fn("fail", Some(0), |ctx, _| {
+            Err(ctx.error(format!("Failed on line {}.", ctx.line())))
+        });
+
+        let err = lox
+            .eval("var a = 1;\n\nprint fail();\nprint 2;")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "[line 3] Error at ')': Failed on line 3.");
+    }
+
+    #[test]
+    fn errors_stop_the_script() {
+        let output = Capture::new();
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+        lox.register_fn("fail", Some(0), |ctx, _| Err(ctx.error("No.")));
+
+        match lox.eval("print 1; fail(); print 2;") {
+            Err(crate::error::LoxError::Runtime(err)) => assert_eq!(err.kind, ErrorKind::Error),
+            _ => panic!("expected a runtime error"),
+        }
+        assert_eq!(output.contents(), "1\n");
+    }
+
+    #[test]
+    fn the_context_reaches_globals_and_output() {
+        let output = Capture::new();
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+        lox.register_fn("bump", Some(0), |ctx, _| {
+            let count = match ctx.get_global("count") {
+                Some(Value::Number(count)) => count,
+                _ => 0.0,
+            };
+            ctx.set_global("count", Value::Number(count + 1.0));
+            writeln!(ctx.output(), "bumped").unwrap();
+            Ok(Value::Nil)
+        });
+
+        lox.eval("bump(); bump();").unwrap();
+        assert_eq!(lox.get_global("count"), Some(Value::Number(2.0)));
+        assert_eq!(output.contents(), "bumped\nbumped\n");
+    }
+
+    #[test]
+    fn host_functions_print_like_natives() {
+        let mut lox = Lox::new();
+        lox.register_fn("host", Some(0), |_, _| Ok(Value::Nil));
+
+        assert_eq!(lox.eval("host").unwrap().to_string(), "<native fn host>");
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use crate::ast::statement::{IfStmt, Stmt};
+
+use super::Instrument;
+
+/// Counts how often each line holding a statement runs, and which way each
+/// `if` goes, for one source file.
+pub struct Coverage {
+    path: String,
+    lines: BTreeMap<usize, u64>,
+    /// How often the then and the else branch were taken, keyed by the line and
+    /// column of the `if`.
+    branches: BTreeMap<(usize, usize), [u64; 2]>,
+}
+
+impl Coverage {
+    /// Starts every statement line and `if` of `statements` at zero, so the
+    /// ones that never run are reported too.
+    pub fn new(path: &str, statements: &[Stmt]) -> Self {
+        let mut coverage = Self {
+            path: String::from(path),
+            lines: BTreeMap::new(),
+            branches: BTreeMap::new(),
+        };
+
+        for stmt in statements {
+            coverage.add(stmt);
+        }
+
+        coverage
+    }
+
+    fn add(&mut self, stmt: &Stmt) {
+        self.lines.insert(stmt.first_token().line, 0);
+
+        match stmt {
+            Stmt::Block(block) => {
+                for stmt in &block.statements {
+                    self.add(stmt);
+                }
+            }
+            Stmt::If(stmt) => {
+                self.branches
+                    .insert((stmt.keyword.line, stmt.keyword.column), [0, 0]);
+                self.add(&stmt.then_branch);
+                if let Some(else_branch) = &stmt.else_branch {
+                    self.add(else_branch);
+                }
+            }
+            Stmt::Test(test) => self.add(&test.body),
+            Stmt::Expression(_) | Stmt::Print(_) | Stmt::Var(_) => {}
+        }
+    }
+
+    /// Writes one LCOV record for the file. Each `if` is its own block with the
+    /// then branch numbered 0 and the else branch (taken when there is none and
+    /// the condition is false) numbered 1.
+    pub fn write_lcov(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{}", self.path)?;
+
+        for (block, ((line, _), taken)) in self.branches.iter().enumerate() {
+            let evaluated = taken.iter().any(|&count| count > 0);
+            for (branch, count) in taken.iter().enumerate() {
+                match evaluated {
+                    true => writeln!(out, "BRDA:{},{},{},{}", line, block, branch, count)?,
+                    false => writeln!(out, "BRDA:{},{},{},-", line, block, branch)?,
+                }
+            }
+        }
+        let branches_hit = self
+            .branches
+            .values()
+            .flatten()
+            .filter(|&&count| count > 0)
+            .count();
+        writeln!(out, "BRF:{}", self.branches.len() * 2)?;
+        writeln!(out, "BRH:{}", branches_hit)?;
+
+        for (line, hits) in &self.lines {
+            writeln!(out, "DA:{},{}", line, hits)?;
+        }
+        let lines_hit = self.lines.values().filter(|&&hits| hits > 0).count();
+        writeln!(out, "LF:{}", self.lines.len())?;
+        writeln!(out, "LH:{}", lines_hit)?;
+
+        writeln!(out, "end_of_record")
+    }
+}
+
+impl Instrument for Coverage {
+    fn enter_statement(&mut self, stmt: &Stmt) {
+        *self.lines.entry(stmt.first_token().line).or_default() += 1;
+    }
+
+    fn branch(&mut self, stmt: &IfStmt, taken: bool) {
+        let counts = self
+            .branches
+            .entry((stmt.keyword.line, stmt.keyword.column))
+            .or_default();
+        counts[if taken { 0 } else { 1 }] += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{ast::parser::Parser, interpreter::Interpreter, lexer::scanner::Scanner};
+
+    fn lcov(source: &str) -> String {
+        let statements = Parser::new(Scanner::new(String::from(source)).scan_tokens()).parse();
+        let coverage = Rc::new(RefCell::new(Coverage::new("script.lox", &statements)));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(io::sink());
+        interpreter.instrument = Some(Box::new(coverage.clone()));
+        interpreter.interpret(&statements);
+
+        let mut out = Vec::new();
+        coverage.borrow().write_lcov(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn branches_and_lines_of_if_else_and_short_circuits() {
+        let source = "\
+var a = true;
+if (a and false) {
+  print \"then\";
+} else {
+  print \"else\";
+}
+if (a or undefined) { print \"short\"; }
+if (false) {
+  print \"never\";
+}
+";
+        assert_eq!(
+            lcov(source),
+            "\
+TN:
+SF:script.lox
+BRDA:2,0,0,0
+BRDA:2,0,1,1
+BRDA:7,1,0,1
+BRDA:7,1,1,0
+BRDA:8,2,0,0
+BRDA:8,2,1,1
+BRF:6
+BRH:3
+DA:1,1
+DA:2,1
+DA:3,0
+DA:5,1
+DA:7,2
+DA:8,1
+DA:9,0
+LF:7
+LH:5
+end_of_record
+"
+        );
+    }
+
+    #[test]
+    fn an_if_that_never_runs_has_unevaluated_branches() {
+        let source = "\
+if (false) {
+  if (true) { print 1; }
+}
+";
+        assert_eq!(
+            lcov(source),
+            "\
+TN:
+SF:script.lox
+BRDA:1,0,0,0
+BRDA:1,0,1,1
+BRDA:2,1,0,-
+BRDA:2,1,1,-
+BRF:4
+BRH:1
+DA:1,1
+DA:2,0
+LF:2
+LH:1
+end_of_record
+"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+pub mod coverage;
+pub mod profile;
+pub mod trace;
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    ast::{
+        expr::Expr,
+        statement::{IfStmt, Stmt},
+    },
+    lexer::token::Token,
+    value::Value,
+};
+
+/// Observes a running script. The interpreter calls these around each statement
+/// and function call when an instrument is installed, and skips them entirely
+/// otherwise. Every method does nothing unless overridden.
+pub trait Instrument {
+    fn enter_statement(&mut self, _stmt: &Stmt) {}
+
+    /// Called once the statement has finished, whether or not it failed.
+    fn exit_statement(&mut self, _stmt: &Stmt) {}
+
+    /// Called once the condition of `stmt` is known; `taken` is true for the then
+    /// branch.
+    fn branch(&mut self, _stmt: &IfStmt, _taken: bool) {}
+
+    /// Called with the value of each expression that evaluates without error.
+    fn value(&mut self, _expr: &Expr, _value: &Value) {}
+
+    fn enter_call(&mut self, _name: &str, _paren: &Token) {}
+
+    fn exit_call(&mut self, _name: &str) {}
+}
+
+/// Lets the caller keep a handle on an instrument to read its results after the
+/// interpreter has run.
+impl<T: Instrument> Instrument for Rc<RefCell<T>> {
+    fn enter_statement(&mut self, stmt: &Stmt) {
+        self.borrow_mut().enter_statement(stmt)
+    }
+
+    fn exit_statement(&mut self, stmt: &Stmt) {
+        self.borrow_mut().exit_statement(stmt)
+    }
+
+    fn branch(&mut self, stmt: &IfStmt, taken: bool) {
+        self.borrow_mut().branch(stmt, taken)
+    }
+
+    fn value(&mut self, expr: &Expr, value: &Value) {
+        self.borrow_mut().value(expr, value)
+    }
+
+    fn enter_call(&mut self, name: &str, paren: &Token) {
+        self.borrow_mut().enter_call(name, paren)
+    }
+
+    fn exit_call(&mut self, name: &str) {
+        self.borrow_mut().exit_call(name)
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{ast::statement::Stmt, lexer::token::Token};
+
+use super::Instrument;
+
+/// Hit count and time spent for one line or function. Self time leaves out
+/// the statements and calls nested inside.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Entry {
+    pub hits: u64,
+    pub total: Duration,
+    pub own: Duration,
+}
+
+impl Entry {
+    fn record(&mut self, total: Duration, own: Duration) {
+        self.hits += 1;
+        self.total += total;
+        self.own += own;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Label {
+    Line(usize),
+    Function(String),
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Label::Line(line) => write!(f, "line {}", line),
+            Label::Function(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+struct Frame {
+    label: Label,
+    started: Instant,
+    children: Duration,
+}
+
+/// Times every statement and function call of a script.
+pub struct Profiler {
+    root: String,
+    pub lines: BTreeMap<usize, Entry>,
+    pub functions: BTreeMap<String, Entry>,
+    stack: Vec<Frame>,
+    /// Self time keyed by the `;`-joined frames leading to it.
+    stacks: BTreeMap<String, Duration>,
+}
+
+impl Profiler {
+    /// `root` names the outermost frame of the collapsed stacks, usually the script.
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: String::from(root),
+            lines: BTreeMap::new(),
+            functions: BTreeMap::new(),
+            stack: Vec::new(),
+            stacks: BTreeMap::new(),
+        }
+    }
+
+    fn enter(&mut self, label: Label) {
+        self.stack.push(Frame {
+            label,
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn exit(&mut self) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+
+        let stopped = Instant::now();
+        let total = stopped.duration_since(frame.started);
+        let own = total.saturating_sub(frame.children);
+
+        let mut path = self.root.clone();
+        for outer in &self.stack {
+            path.push_str(&format!(";{}", outer.label));
+        }
+        path.push_str(&format!(";{}", frame.label));
+        *self.stacks.entry(path).or_default() += own;
+
+        // Time inside an outer frame with the same label already counts towards
+        // its total.
+        let outer = self.stack.iter().any(|outer| outer.label == frame.label);
+        let counted = if outer { Duration::ZERO } else { total };
+
+        match frame.label {
+            Label::Line(line) => self.lines.entry(line).or_default().record(counted, own),
+            Label::Function(name) => self.functions.entry(name).or_default().record(counted, own),
+        }
+
+        // The bookkeeping above is the profiler's time, not the parent's.
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += stopped.elapsed() + total;
+        }
+    }
+
+    /// Writes the lines and then the functions, each sorted by self time with the
+    /// slowest first.
+    pub fn report(&self, out: &mut dyn Write) -> io::Result<()> {
+        let lines = self
+            .lines
+            .iter()
+            .map(|(line, entry)| (format!("line {}", line), *entry));
+        write_table(out, "Line", lines.collect())?;
+
+        writeln!(out)?;
+
+        let functions = self
+            .functions
+            .iter()
+            .map(|(name, entry)| (name.clone(), *entry));
+        write_table(out, "Function", functions.collect())
+    }
+
+    /// The profile in the collapsed-stack format read by flame graph tools: one
+    /// line per distinct stack, weighted by its self time in nanoseconds.
+    pub fn collapsed(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(path, own)| format!("{} {}\n", path, own.as_nanos()))
+            .collect()
+    }
+}
+
+fn write_table(
+    out: &mut dyn Write,
+    heading: &str,
+    mut rows: Vec<(String, Entry)>,
+) -> io::Result<()> {
+    rows.sort_by(|(a_name, a), (b_name, b)| b.own.cmp(&a.own).then_with(|| a_name.cmp(b_name)));
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain([heading.len()])
+        .max()
+        .unwrap_or_default();
+
+    writeln!(
+        out,
+        "{:<width$}  {:>8}  {:>12}  {:>12}",
+        heading, "Hits", "Self (ms)", "Total (ms)"
+    )?;
+    for (name, entry) in rows {
+        writeln!(
+            out,
+            "{:<width$}  {:>8}  {:>12.3}  {:>12.3}",
+            name,
+            entry.hits,
+            entry.own.as_secs_f64() * 1e3,
+            entry.total.as_secs_f64() * 1e3
+        )?;
+    }
+
+    Ok(())
+}
+
+impl Instrument for Profiler {
+    fn enter_statement(&mut self, stmt: &Stmt) {
+        self.enter(Label::Line(stmt.first_token().line));
+    }
+
+    fn exit_statement(&mut self, _: &Stmt) {
+        self.exit();
+    }
+
+    fn enter_call(&mut self, name: &str, _: &Token) {
+        self.enter(Label::Function(String::from(name)));
+    }
+
+    fn exit_call(&mut self, _: &str) {
+        self.exit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{ast::parser::Parser, interpreter::Interpreter, lexer::scanner::Scanner};
+
+    fn profile(source: &str) -> Profiler {
+        let statements = Parser::new(Scanner::new(String::from(source)).scan_tokens()).parse();
+        let profiler = Rc::new(RefCell::new(Profiler::new("script.lox")));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(io::sink());
+        interpreter.instrument = Some(Box::new(profiler.clone()));
+        interpreter.interpret(&statements);
+        drop(interpreter);
+
+        Rc::try_unwrap(profiler).ok().unwrap().into_inner()
+    }
+
+    fn entry(hits: u64, own_us: u64, total_us: u64) -> Entry {
+        Entry {
+            hits,
+            own: Duration::from_micros(own_us),
+            total: Duration::from_micros(total_us),
+        }
+    }
+
+    #[test]
+    fn tables_are_sorted_by_self_time() {
+        let mut profiler = Profiler::new("script.lox");
+        profiler.lines.insert(1, entry(1, 250, 2000));
+        profiler.lines.insert(12, entry(3, 1500, 1750));
+        profiler
+            .functions
+            .insert(String::from("len"), entry(2, 10, 10));
+        profiler
+            .functions
+            .insert(String::from("replaceAll"), entry(1, 500, 500));
+
+        let mut out = Vec::new();
+        profiler.report(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Line         Hits     Self (ms)    Total (ms)
+line 12         3         1.500         1.750
+line 1          1         0.250         2.000
+
+Function        Hits     Self (ms)    Total (ms)
+replaceAll         1         0.500         0.500
+len                2         0.010         0.010
+"
+        );
+    }
+
+    #[test]
+    fn collapsed_stacks_nest_calls_inside_lines() {
+        let profiler = profile("{\n  print len(\"ab\");\n}\nprint 1;\n");
+        let collapsed = profiler.collapsed();
+
+        let stacks: Vec<&str> = collapsed
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            [
+                "script.lox;line 1",
+                "script.lox;line 1;line 2",
+                "script.lox;line 1;line 2;len",
+                "script.lox;line 4",
+            ]
+        );
+        for line in collapsed.lines() {
+            let weight = line.rsplit_once(' ').unwrap().1;
+            assert!(weight.parse::<u128>().is_ok(), "bad weight in {:?}", line);
+        }
+
+        assert_eq!(profiler.lines[&2].hits, 1);
+        assert_eq!(profiler.functions["len"].hits, 1);
+    }
+
+    #[test]
+    fn self_time_leaves_out_nested_frames() {
+        let profiler = profile("{\n  print len(\"ab\");\n}\n");
+
+        let block = profiler.lines[&1];
+        let print = profiler.lines[&2];
+        assert!(block.own + print.total <= block.total);
+        assert!(print.own + profiler.functions["len"].total <= print.total);
+    }
+
+    #[test]
+    fn recursion_on_one_line_counts_its_time_once() {
+        let profiler = profile("{ { print 1; } }\n");
+
+        let line = profiler.lines[&1];
+        assert_eq!(line.hits, 3);
+        assert!(line.own <= line.total);
+        assert!(profiler.collapsed().starts_with("script.lox;line 1 "));
+        assert!(profiler
+            .collapsed()
+            .contains("script.lox;line 1;line 1;line 1 "));
+    }
+}
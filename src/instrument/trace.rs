@@ -0,0 +1,72 @@
+use std::{io::Write, ops::RangeInclusive};
+
+use crate::{
+    ast::{expr::Expr, printer::AstPrinter, statement::Stmt},
+    value::Value,
+};
+
+use super::Instrument;
+
+/// Logs each statement as it starts and each expression's value as it is
+/// produced, indented by how many blocks enclose it. Failures to write are
+/// ignored so that tracing never changes how the script runs.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    lines: RangeInclusive<usize>,
+    depth: usize,
+    printer: AstPrinter,
+}
+
+impl Tracer {
+    /// Only what starts on one of `lines` is logged.
+    pub fn new(out: Box<dyn Write>, lines: RangeInclusive<usize>) -> Self {
+        Self {
+            out,
+            lines,
+            depth: 0,
+            printer: AstPrinter::new(),
+        }
+    }
+
+    fn log(&mut self, line: usize, indent: usize, text: &str) {
+        if self.lines.contains(&line) {
+            let _ = writeln!(self.out, "[line {}] {}{}", line, "  ".repeat(indent), text);
+        }
+    }
+
+    /// The statement without the statements nested in it, which are logged
+    /// when they run.
+    fn describe(&mut self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(_) => String::from("(block)"),
+            Stmt::If(stmt) => format!("(if {})", self.printer.print_expr(&stmt.condition)),
+            Stmt::Test(stmt) => format!("(test {})", stmt.name.lexeme),
+            stmt => self.printer.print_stmt(stmt),
+        }
+    }
+}
+
+impl Instrument for Tracer {
+    fn enter_statement(&mut self, stmt: &Stmt) {
+        let text = self.describe(stmt);
+        self.log(stmt.first_token().line, self.depth, &text);
+
+        if let Stmt::Block(_) = stmt {
+            self.depth += 1;
+        }
+    }
+
+    fn exit_statement(&mut self, stmt: &Stmt) {
+        if let Stmt::Block(_) = stmt {
+            self.depth -= 1;
+        }
+    }
+
+    fn value(&mut self, expr: &Expr, value: &Value) {
+        let text = match value {
+            Value::String(s) => format!("{} => \"{}\"", self.printer.print_expr(expr), s),
+            value => format!("{} => {}", self.printer.print_expr(expr), value),
+        };
+        self.log(expr.first_token().line, self.depth + 1, &text);
+    }
+}
@@ -1,58 +1,274 @@
+use std::{collections::HashMap, io::Write, rc::Rc};
+
+use regex::Regex;
+
 use crate::{
     ast::{
         expr::{
-            AssignExpr, BinaryExpr, Expr, GroupingExpr, LiteralExpr, LogicalExpr, UnaryExpr,
-            VariableExpr,
+            AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, LiteralExpr, LogicalExpr,
+            UnaryExpr, VariableExpr,
         },
-        statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, Stmt, VarStmt},
+        statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, Stmt, TestStmt, VarStmt},
     },
+    debugger::Hook,
     environment::Environment,
-    error::RuntimeError,
+    error::{self, ErrorKind, RuntimeError},
     expr_visitor::ExprVisitor,
-    lexer::token_type::TokenType,
+    host::{Context, HostFn, HostFunction},
+    instrument::Instrument,
+    interrupt::InterruptHandle,
+    lexer::{token::Token, token_type::TokenType},
+    limits::{self, Limit, Limits},
+    native::{
+        self,
+        random::Rng,
+        time::{Clock, SystemClock},
+    },
     stmt_visitor::StmtVisitor,
     value::Value,
 };
 
 pub struct Interpreter {
     pub environment: Environment,
+    pub regexes: HashMap<String, Regex>,
+    pub clock: Rc<dyn Clock>,
+    pub rng: Rng,
+    pub script_path: Option<String>,
+    pub script_args: Vec<String>,
+    pub output: Box<dyn Write>,
+    pub debugger: Option<Box<dyn Hook>>,
+    pub instrument: Option<Box<dyn Instrument>>,
+    pub limits: Limits,
+    interrupt: InterruptHandle,
+    depth: usize,
+    nesting: usize,
+    steps: u64,
+    allocated: usize,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        let mut environment = Environment::new();
+        native::define_globals(&mut environment);
+
         Self {
-            environment: Environment::new(),
+            environment,
+            regexes: HashMap::new(),
+            clock: Rc::new(SystemClock),
+            rng: Rng::from_time(),
+            script_path: None,
+            script_args: Vec::new(),
+            output: Box::new(std::io::stdout()),
+            debugger: None,
+            instrument: None,
+            limits: Limits::default(),
+            interrupt: InterruptHandle::new(),
+            depth: 0,
+            nesting: 0,
+            steps: 0,
+            allocated: 0,
         }
     }
 
-    pub fn interpret(&mut self, statements: &Vec<Stmt>) {
+    /// Defines a global that calls `function`, checking the argument count
+    /// first if `arity` is given.
+    pub fn define_host_function(
+        &mut self,
+        name: &str,
+        arity: Option<usize>,
+        function: Box<HostFn>,
+    ) {
+        self.environment.define(
+            String::from(name),
+            Value::HostFunction(Rc::new(HostFunction {
+                name: String::from(name),
+                arity,
+                function,
+            })),
+        );
+    }
+
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Runs `statements`, reporting a runtime error and carrying on with the next
+    /// one. Returns the status passed to `exit` if the script called it. Hitting
+    /// a limit or being interrupted stops the script.
+    pub fn interpret(&mut self, statements: &Vec<Stmt>) -> Option<i32> {
+        self.interrupt.clear();
         for stmt in statements {
-            self.execute(stmt);
+            if let Err(err) = self.execute(stmt) {
+                if let ErrorKind::Exit(code) = err.kind {
+                    return Some(code);
+                }
+
+                error::runtime_error(&err);
+                if err.kind != ErrorKind::Error {
+                    break;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// How many statements enclose the one being executed; zero at the top level.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// A handle that stops this interpreter's script from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Fails with `ErrorKind::Interrupted` at `token` if another thread asked the
+    /// script to stop; natives call it between iterations of long loops.
+    pub fn check_interrupt(&self, token: &Token) -> Result<(), RuntimeError> {
+        if self.interrupt.take() {
+            return Err(RuntimeError::interrupted(token.clone()));
+        }
+
+        Ok(())
+    }
+
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        self.check_interrupt(stmt.first_token())?;
+
+        // The hook is taken out while it runs so it can evaluate in this interpreter.
+        if let Some(mut debugger) = self.debugger.take() {
+            let result = debugger.before_statement(self, stmt);
+            self.debugger = Some(debugger);
+            result?;
+        }
+
+        self.enter(stmt.first_token())?;
+        if let Some(instrument) = &mut self.instrument {
+            instrument.enter_statement(stmt);
+        }
+
+        self.depth += 1;
+        let result = stmt.accept(self);
+        self.depth -= 1;
+        self.nesting -= 1;
+
+        if let Some(instrument) = &mut self.instrument {
+            instrument.exit_statement(stmt);
+        }
+
+        result
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.check_interrupt(expr.first_token())?;
+        self.enter(expr.first_token())?;
+        let result = expr.accept(self);
+        self.nesting -= 1;
+
+        if let (Some(instrument), Ok(value)) = (&mut self.instrument, &result) {
+            instrument.value(expr, value);
+        }
+
+        result
+    }
+
+    /// Runs `function`, telling the instrument when the call to `name` starts and ends.
+    fn call(
+        &mut self,
+        name: &str,
+        paren: &Token,
+        function: impl FnOnce(&mut Interpreter) -> Result<Value, RuntimeError>,
+    ) -> Result<Value, RuntimeError> {
+        if let Some(instrument) = &mut self.instrument {
+            instrument.enter_call(name, paren);
+        }
+
+        let result = function(self);
+
+        if let Some(instrument) = &mut self.instrument {
+            instrument.exit_call(name);
+        }
+
+        result
+    }
+
+    /// Counts a step and a level of nesting against the limits.
+    fn enter(&mut self, token: &Token) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        if let Some(steps) = self.limits.steps {
+            if self.steps > steps {
+                return Err(RuntimeError::limit(
+                    token.clone(),
+                    Limit::Steps,
+                    format!("Step limit of {} exceeded.", steps),
+                ));
+            }
+        }
+
+        if let Some(depth) = self.limits.depth {
+            if self.nesting >= depth {
+                return Err(RuntimeError::limit(
+                    token.clone(),
+                    Limit::Depth,
+                    format!("Depth limit of {} exceeded.", depth),
+                ));
+            }
         }
+
+        self.nesting += 1;
+        Ok(())
     }
 
-    fn execute(&mut self, stmt: &Stmt) {
-        stmt.accept(self);
+    /// Counts `bytes` of new strings or collections against the memory limit.
+    /// Call it before building the value where the size is known up front, so
+    /// that a value over the limit is never built.
+    pub fn allocate(&mut self, token: &Token, bytes: usize) -> Result<(), RuntimeError> {
+        let allocated = self.allocated.saturating_add(bytes);
+        match self.limits.memory {
+            Some(memory) if allocated > memory => Err(RuntimeError::limit(
+                token.clone(),
+                Limit::Memory,
+                format!("Memory limit of {} bytes exceeded.", memory),
+            )),
+            _ => {
+                self.allocated = allocated;
+                Ok(())
+            }
+        }
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
-        expr.accept(self)
+    /// Like `allocate`, charging for everything `value` holds, for values whose
+    /// size is only known once they are built.
+    pub fn allocate_value(&mut self, token: &Token, value: Value) -> Result<Value, RuntimeError> {
+        self.allocate(token, limits::size_of(&value))?;
+        Ok(value)
     }
 
-    fn execute_block(&mut self, statements: &Vec<Stmt>, environment: Environment) {
+    /// Starts the step and memory counts over, e.g. for the next snippet.
+    pub fn reset_usage(&mut self) {
+        self.steps = 0;
+        self.allocated = 0;
+    }
+
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), RuntimeError> {
         let previous = self.environment.clone();
 
         self.environment = environment;
 
-        for stmt in statements {
-            self.execute(stmt);
-        }
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
 
         self.environment = previous;
+
+        result
     }
 }
 
-fn is_truthy(value: &Value) -> bool {
+pub fn is_truthy(value: &Value) -> bool {
     match value {
         Value::Nil => false,
         Value::Bool(b) => *b,
@@ -60,69 +276,88 @@ fn is_truthy(value: &Value) -> bool {
     }
 }
 
-fn is_equal(left: &Value, right: &Value) -> bool {
+pub fn is_equal(left: &Value, right: &Value) -> bool {
     match (left, right) {
         (Value::Nil, Value::Nil) => true,
         (Value::Bool(l), Value::Bool(r)) => l == r,
         (Value::Number(l), Value::Number(r)) => l == r,
         (Value::String(l), Value::String(r)) => l == r,
+        (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+        (Value::Map(l), Value::Map(r)) => Rc::ptr_eq(l, r),
+        (Value::NativeFunction(l), Value::NativeFunction(r)) => l == r,
+        (Value::HostFunction(l), Value::HostFunction(r)) => Rc::ptr_eq(l, r),
         _ => false,
     }
 }
 
-impl StmtVisitor<()> for Interpreter {
-    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+fn check_arity(paren: &Token, arity: Option<usize>, count: usize) -> Result<(), RuntimeError> {
+    match arity {
+        Some(arity) if arity != count => Err(RuntimeError::new(
+            paren.clone(),
+            format!("Expected {} arguments but got {}.", arity, count),
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Result<(), RuntimeError> {
         self.execute_block(
             &stmt.statements,
             Environment::from(self.environment.clone()),
-        );
+        )
     }
 
-    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
-        if let Err(e) = self.evaluate(&stmt.expression) {
-            println!("{}", e)
-        }
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> Result<(), RuntimeError> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
     }
 
-    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
-        match self.evaluate(&stmt.condition) {
-            Ok(value) => {
-                if is_truthy(&value) {
-                    self.execute(&stmt.then_branch);
-                } else if let Some(else_branch) = &stmt.else_branch {
-                    self.execute(else_branch);
-                }
-            }
-            Err(e) => println!("{}", e),
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<(), RuntimeError> {
+        let condition = is_truthy(&self.evaluate(&stmt.condition)?);
+
+        if let Some(instrument) = &mut self.instrument {
+            instrument.branch(stmt, condition);
         }
-    }
 
-    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
-        match self.evaluate(&stmt.expression) {
-            Ok(value) => println!("{}", value),
-            Err(e) => println!("{}", e),
+        if condition {
+            self.execute(&stmt.then_branch)
+        } else if let Some(else_branch) = &stmt.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
         }
     }
 
-    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<(), RuntimeError> {
+        let value = self.evaluate(&stmt.expression)?;
+        writeln!(self.output, "{}", value).map_err(|err| {
+            RuntimeError::new(
+                stmt.keyword.clone(),
+                format!("Failed to write output: {}", err),
+            )
+        })
+    }
+
+    /// Tests are skipped when a file is run; `rslox test` runs their bodies.
+    fn visit_test_stmt(&mut self, _: &TestStmt) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Result<(), RuntimeError> {
         let value = match &stmt.initializer {
-            Some(expr) => match self.evaluate(expr) {
-                Ok(value) => value,
-                Err(e) => {
-                    println!("{}", e);
-                    Value::Nil
-                }
-            },
+            Some(expr) => self.evaluate(expr)?,
             None => Value::Nil,
         };
 
         self.environment.define(stmt.name.lexeme.clone(), value);
+        Ok(())
     }
 }
 
 impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<Value, RuntimeError> {
-        let value = self.evaluate(&expr.value).unwrap();
+        let value = self.evaluate(&expr.value)?;
         match self.environment.assign(expr.name.clone(), value.clone()) {
             Ok(_) => Ok(value),
             Err(err) => Err(err),
@@ -130,8 +365,8 @@ impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
     }
 
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Result<Value, RuntimeError> {
-        let left = self.evaluate(&expr.left).unwrap();
-        let right = self.evaluate(&expr.right).unwrap();
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
 
         match expr.operator.token_type {
             TokenType::Greater => match (left, right) {
@@ -173,7 +408,10 @@ impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
             },
             TokenType::Plus => match (left, right) {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
-                (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
+                (Value::String(l), Value::String(r)) => {
+                    self.allocate(&expr.operator, l.len() + r.len())?;
+                    Ok(Value::String(l + &r))
+                }
                 _ => Err(RuntimeError::new(
                     expr.operator.clone(),
                     String::from("Operands must be two numbers or two strings."),
@@ -206,19 +444,45 @@ impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
         }
     }
 
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut arguments = Vec::new();
+        for argument in &expr.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        match callee {
+            Value::NativeFunction(function) => {
+                check_arity(&expr.paren, function.arity, arguments.len())?;
+                self.call(function.name, &expr.paren, |interpreter| {
+                    (function.function)(interpreter, &expr.paren, &arguments)
+                })
+            }
+            Value::HostFunction(function) => {
+                check_arity(&expr.paren, function.arity, arguments.len())?;
+                let value = self.call(&function.name, &expr.paren, |interpreter| {
+                    (function.function)(&mut Context::new(interpreter, &expr.paren), &arguments)
+                })?;
+                self.allocate_value(&expr.paren, value)
+            }
+            _ => Err(RuntimeError::new(
+                expr.paren.clone(),
+                String::from("Can only call functions and classes."),
+            )),
+        }
+    }
+
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Result<Value, RuntimeError> {
         self.evaluate(&expr.expression)
     }
 
-    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Value, RuntimeError> {
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Result<Value, RuntimeError> {
         Ok(expr.value.clone().unwrap_or(Value::Nil))
     }
 
     fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<Value, RuntimeError> {
-        let left = match self.evaluate(&expr.left) {
-            Ok(value) => value,
-            Err(error) => return Err(error),
-        };
+        let left = self.evaluate(&expr.left)?;
 
         if expr.operator.token_type == TokenType::Or {
             if is_truthy(&left) {
@@ -232,7 +496,7 @@ impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
     }
 
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Result<Value, RuntimeError> {
-        let right = self.evaluate(&expr.right).unwrap();
+        let right = self.evaluate(&expr.right)?;
 
         match expr.operator.token_type {
             TokenType::Bang => Ok(Value::Bool(!is_truthy(&right))),
@@ -247,7 +511,63 @@ impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
         }
     }
 
-    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Value, RuntimeError> {
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> Result<Value, RuntimeError> {
         self.environment.get(expr.name.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Interpreter;
+    use crate::{
+        ast::parser::Parser, capture::Capture, error, lexer::scanner::Scanner, lox::Lox,
+        value::Value,
+    };
+
+    /// Runs `source` as `rslox` would, returning what it printed and reported.
+    fn interpret(source: &str) -> (Option<i32>, String, String) {
+        let (tokens, _) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        let (statements, _) = Parser::new(tokens).parse_with_errors();
+
+        let output = Capture::new();
+        let reports = Capture::new();
+        let previous = error::set_reporter(Box::new(reports.clone()));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(output.clone());
+        let status = interpreter.interpret(&statements);
+
+        error::set_reporter(previous);
+        (status, output.contents(), reports.contents())
+    }
+
+    #[test]
+    fn a_runtime_error_is_reported_and_the_script_carries_on() {
+        let (status, output, reports) = interpret("print 1;\nprint -\"a\";\nprint 3;");
+        assert_eq!(status, None);
+        assert_eq!(output, "1\n3\n");
+        assert_eq!(
+            reports,
+            "[line 2] Error at '-': Operand must be a number.\n"
+        );
+    }
+
+    #[test]
+    fn exit_stops_the_script_with_its_status() {
+        let (status, output, reports) = interpret("print 1; { exit(3); print 2; } print 4;");
+        assert_eq!(status, Some(3));
+        assert_eq!(output, "1\n");
+        assert_eq!(reports, "");
+    }
+
+    fn eval(source: &str) -> Value {
+        Lox::new().eval(source).unwrap()
+    }
+
+    #[test]
+    fn binary_operators_evaluate_left_to_right() {
+        assert_eq!(eval("1 - 2 - 3"), Value::Number(-4.0));
+        assert_eq!(eval("16 / 4 / 2"), Value::Number(2.0));
+        assert_eq!(eval("2 * 3 - 4 / 2"), Value::Number(4.0));
+    }
+}
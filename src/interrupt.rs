@@ -0,0 +1,34 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Stops a running script from another thread, e.g. when a request times out.
+/// Clones share one flag; the interpreter checks it before each statement and
+/// expression and inside natives that loop. A request made while no script runs
+/// is dropped when the next one starts, so it cannot cancel unrelated work.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the script to stop at its next statement.
+    pub fn interrupt(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Reports whether an interrupt was requested, clearing the request.
+    pub(crate) fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Drops a pending request, e.g. one that arrived after the last script ended.
+    pub(crate) fn clear(&self) {
+        self.requested.store(false, Ordering::Relaxed);
+    }
+}
@@ -2,10 +2,15 @@ use std::collections::HashMap;
 
 use lazy_static::lazy_static;
 
-use crate::{error, lexer::token::Token, lexer::token_type::TokenType, value::Value};
+use crate::{
+    error::{self, ScanError},
+    lexer::token::Token,
+    lexer::token_type::TokenType,
+    value::Value,
+};
 
 lazy_static! {
-    static ref KEYWORDS: HashMap<&'static str, TokenType> = {
+    pub static ref KEYWORDS: HashMap<&'static str, TokenType> = {
         let mut m = HashMap::new();
         m.insert("and", TokenType::And);
         m.insert("class", TokenType::Class);
@@ -27,12 +32,20 @@ lazy_static! {
     };
 }
 
+/// Scans source text into tokens. Offsets into `source` are in bytes; lines
+/// and columns are 1-based, with columns counted in characters.
 pub struct Scanner {
     source: String,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: usize,
+    /// The 1-based column of `current`, kept up to date as characters are consumed.
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    errors: Vec<ScanError>,
+    keep_comments: bool,
 }
 
 impl Scanner {
@@ -43,12 +56,39 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+            errors: Vec::new(),
+            keep_comments: false,
+        }
+    }
+
+    /// A scanner that emits `Comment` tokens instead of discarding comments, for
+    /// tools that work on the source text. The parser does not accept them.
+    pub fn with_comments(source: String) -> Scanner {
+        Scanner {
+            keep_comments: true,
+            ..Scanner::new(source)
         }
     }
 
     pub fn scan_tokens(&mut self) -> Vec<Token> {
+        let (tokens, errors) = self.scan_tokens_with_errors();
+
+        for err in errors {
+            error::error(err.line, &err.message);
+        }
+
+        tokens
+    }
+
+    /// Scans like `scan_tokens`, but hands back the errors instead of reporting them.
+    pub fn scan_tokens_with_errors(&mut self) -> (Vec<Token>, Vec<ScanError>) {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
             self.scan_token();
         }
 
@@ -57,9 +97,10 @@ impl Scanner {
             String::from(""),
             None,
             self.line,
+            self.column,
         ));
 
-        self.tokens.clone()
+        (self.tokens.clone(), self.errors.clone())
     }
 
     fn scan_token(&mut self) {
@@ -107,26 +148,28 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+
+                    self.add_comment();
                 } else if self.match_char('*') {
-                    while self.peek() != '*' && self.peek_next() != '/' && !self.is_at_end() {
-                        if self.peek() == '\n' {
-                            self.line += 1;
+                    while !(self.is_at_end() || self.peek() == '*' && self.peek_next() == '/') {
+                        if self.advance() == '\n' {
+                            self.new_line();
                         }
-                        self.advance();
                     }
 
                     if self.is_at_end() {
-                        error::error(self.line, "Unterminated block comment.");
+                        self.error("Unterminated block comment.");
                     } else {
                         self.advance();
                         self.advance();
+                        self.add_comment();
                     }
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
             '"' => self.string(),
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
             ' ' | '\r' | '\t' => {}
             c => {
                 if c.is_ascii_digit() {
@@ -134,16 +177,17 @@ impl Scanner {
                 } else if c.is_ascii_alphabetic() || c == '_' {
                     self.identifier();
                 } else {
-                    error::error(self.line, "Unexpected character.")
+                    self.error("Unexpected character.")
                 }
             }
         }
     }
 
     fn advance(&mut self) -> char {
-        match self.source.chars().nth(self.current) {
+        match self.source[self.current..].chars().next() {
             Some(c) => {
-                self.current += 1;
+                self.current += c.len_utf8();
+                self.column += 1;
                 c
             }
             None => '\0',
@@ -151,34 +195,33 @@ impl Scanner {
     }
 
     fn peek(&self) -> char {
-        self.source.chars().nth(self.current).unwrap_or('\0')
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        self.source.chars().nth(self.current + 1).unwrap_or('\0')
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn match_char(&mut self, expected: char) -> bool {
-        if self.is_at_end() || self.source.chars().nth(self.current).unwrap() != expected {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.current += expected.len_utf8();
+        self.column += 1;
 
         true
     }
 
     fn string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-
-            self.advance();
         }
 
         if self.is_at_end() {
-            error::error(self.line, "Unterminated string.");
+            self.error("Unterminated string.");
             return;
         }
 
@@ -219,6 +262,12 @@ impl Scanner {
         self.add_token(token_type.clone());
     }
 
+    fn add_comment(&mut self) {
+        if self.keep_comments {
+            self.add_token(TokenType::Comment);
+        }
+    }
+
     fn add_token(&mut self, token_type: TokenType) {
         self.add_token_literal(token_type, None);
     }
@@ -229,11 +278,142 @@ impl Scanner {
             token_type,
             String::from(text),
             literal,
-            self.line,
+            self.start_line,
+            self.start_column,
         ));
     }
 
+    /// Records that the character just consumed ends a line.
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 1;
+    }
+
+    fn error(&mut self, message: &str) {
+        self.errors.push(ScanError {
+            line: self.start_line,
+            column: self.start_column,
+            message: String::from(message),
+        });
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(source: &str) -> Vec<(TokenType, String, usize, usize)> {
+        let (tokens, errors) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        assert!(errors.is_empty(), "{:?}", errors);
+        tokens
+            .into_iter()
+            .map(|token| (token.token_type, token.lexeme, token.line, token.column))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_carry_lines_and_columns() {
+        assert_eq!(
+            positions("var x = 1;\n  print x >= 2.5;"),
+            [
+                (TokenType::Var, String::from("var"), 1, 1),
+                (TokenType::Identifier, String::from("x"), 1, 5),
+                (TokenType::Equal, String::from("="), 1, 7),
+                (TokenType::Number, String::from("1"), 1, 9),
+                (TokenType::Semicolon, String::from(";"), 1, 10),
+                (TokenType::Print, String::from("print"), 2, 3),
+                (TokenType::Identifier, String::from("x"), 2, 9),
+                (TokenType::GreaterEqual, String::from(">="), 2, 11),
+                (TokenType::Number, String::from("2.5"), 2, 14),
+                (TokenType::Semicolon, String::from(";"), 2, 17),
+                (TokenType::Eof, String::new(), 2, 18),
+            ]
+        );
+    }
+
+    #[test]
+    fn multiline_strings_keep_their_start_position() {
+        let tokens = positions("\"a\nb\" x");
+        assert_eq!(
+            tokens[0],
+            (TokenType::String, String::from("\"a\nb\""), 1, 1)
+        );
+        assert_eq!(tokens[1], (TokenType::Identifier, String::from("x"), 2, 4));
+    }
+
+    #[test]
+    fn literals_are_parsed() {
+        let (tokens, _) = Scanner::new(String::from("\"hi\" 12.5")).scan_tokens_with_errors();
+        assert_eq!(tokens[0].literal, Some(Value::String(String::from("hi"))));
+        assert_eq!(tokens[1].literal, Some(Value::Number(12.5)));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_text() {
+        let (_, errors) = Scanner::new(String::from("x\n  @ \"open")).scan_tokens_with_errors();
+        let errors: Vec<_> = errors
+            .into_iter()
+            .map(|err| (err.line, err.column, err.message))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (2, 3, String::from("Unexpected character.")),
+                (2, 5, String::from("Unterminated string.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_ascii_text_counts_columns_in_characters() {
+        assert_eq!(
+            positions("print \"é\"; // ü\n  x;"),
+            [
+                (TokenType::Print, String::from("print"), 1, 1),
+                (TokenType::String, String::from("\"é\""), 1, 7),
+                (TokenType::Semicolon, String::from(";"), 1, 10),
+                (TokenType::Identifier, String::from("x"), 2, 3),
+                (TokenType::Semicolon, String::from(";"), 2, 4),
+                (TokenType::Eof, String::new(), 2, 5),
+            ]
+        );
+
+        let (tokens, errors) = Scanner::new(String::from("\"日本\" ° y")).scan_tokens_with_errors();
+        assert_eq!(tokens[0].literal, Some(Value::String(String::from("日本"))));
+        assert_eq!(tokens[1].column, 8);
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (1, 6));
+    }
+
+    #[test]
+    fn columns_restart_inside_multiline_strings_and_comments() {
+        assert_eq!(
+            positions("\"a\nbc\" x /* \n */ y"),
+            [
+                (TokenType::String, String::from("\"a\nbc\""), 1, 1),
+                (TokenType::Identifier, String::from("x"), 2, 5),
+                (TokenType::Identifier, String::from("y"), 3, 5),
+                (TokenType::Eof, String::new(), 3, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_dropped_unless_asked_for() {
+        let source = "// line\nx /* block */";
+        let (tokens, _) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        assert_eq!(tokens.len(), 2);
+
+        let (tokens, _) = Scanner::with_comments(String::from(source)).scan_tokens_with_errors();
+        let comments: Vec<_> = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Comment)
+            .map(|token| token.lexeme.as_str())
+            .collect();
+        assert_eq!(comments, ["// line", "/* block */"]);
+    }
+}
@@ -1,11 +1,16 @@
-use crate::{lexer::token_type::TokenType, value::Value};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::{ast::json::literal, lexer::token_type::TokenType, value::Value};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
+    #[serde(rename = "type")]
     pub token_type: TokenType,
     pub lexeme: String,
+    #[serde(with = "literal")]
     pub literal: Option<Value>,
     pub line: usize,
+    pub column: usize,
 }
 
 impl Token {
@@ -14,12 +19,14 @@ impl Token {
         lexeme: String,
         literal: Option<Value>,
         line: usize,
+        column: usize,
     ) -> Token {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
         }
     }
 }
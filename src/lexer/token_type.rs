@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
@@ -46,5 +48,8 @@ pub enum TokenType {
     Var,
     While,
 
+    // Only produced by `Scanner::with_comments`.
+    Comment,
+
     Eof,
 }
@@ -1,87 +1,593 @@
 mod ast;
+mod capture;
+pub mod convert;
+mod debugger;
 mod environment;
 mod error;
 mod expr_visitor;
+mod formatter;
+mod host;
+mod instrument;
 mod interpreter;
+mod interrupt;
 mod lexer;
+mod limits;
+mod linter;
+mod lox;
+mod lsp;
+mod native;
+mod protocol;
+mod repl;
 mod stmt_visitor;
+mod testing;
 mod value;
 
-use std::io::Write;
+use std::{
+    cell::RefCell,
+    io::{BufWriter, IsTerminal, Write},
+    ops::RangeInclusive,
+    path::Path,
+    rc::Rc,
+};
 
-use ast::parser::Parser;
+pub use capture::Capture;
+pub use convert::{ConversionError, FromArgs, FromLox, IntoLox};
+pub use error::{set_reporter, ErrorKind, LoxError, RuntimeError, ScanError, SyntaxError};
+pub use host::{Context, HostFunction};
+pub use interrupt::InterruptHandle;
+pub use lexer::{token::Token, token_type::TokenType};
+pub use limits::{Limit, Limits};
+pub use lox::Lox;
+pub use rslox_derive::{FromLox, IntoLox};
+pub use value::Value;
+
+use ast::{json, parser::Parser, printer::AstPrinter};
+use debugger::console::Console;
+use instrument::{coverage::Coverage, profile::Profiler, trace::Tracer};
 use interpreter::Interpreter;
 use lexer::scanner::Scanner;
+use linter::{LintConfig, Severity};
+use native::time::FixedClock;
 
 pub fn run(args: Vec<String>) {
-    if args.len() > 2 {
-        println!("Usage: rslox [script]");
-        std::process::exit(64);
+    match args.get(1).map(String::as_str) {
+        Some("--tokens") => match args.get(2) {
+            Some(path) => dump_tokens_file(path),
+            None => usage(),
+        },
+        Some("--ast") => match args.get(2) {
+            Some(path) => print_ast_file(path),
+            None => usage(),
+        },
+        Some("--json") => match args.get(2) {
+            Some(path) => print_json_file(path),
+            None => usage(),
+        },
+        Some("--profile") => profile_file(&args[2..]),
+        Some("--trace") => trace_file(&args[2..]),
+        Some("--coverage") => match args.get(2) {
+            Some(file) => cover_file(file, &args[3..]),
+            None => usage(),
+        },
+        Some("--run-json") => match args.get(2) {
+            Some(path) => run_json_file(path),
+            None => usage(),
+        },
+        Some("fmt") => format_files(&args[2..]),
+        Some("lint") => lint_files(&args[2..]),
+        Some("test") => test_files(&args[2..]),
+        Some("lsp") => serve_lsp(),
+        Some("dap") => serve_dap(),
+        Some("debug") => match args.get(2) {
+            Some(path) => debug_file(String::from(path), args[3..].to_vec()),
+            None => usage(),
+        },
+        Some(path) => run_file(String::from(path), args[2..].to_vec()),
+        None => repl::run(),
     }
+}
 
-    if args.len() == 2 {
-        run_file(args[1].clone());
-    } else {
-        run_prompt();
-    }
+fn usage() -> ! {
+    eprintln!("Usage: rslox [script [args...]]");
+    eprintln!("       rslox --tokens script");
+    eprintln!("       rslox --ast script");
+    eprintln!("       rslox --json script");
+    eprintln!("       rslox --run-json ast.json");
+    eprintln!("       rslox --profile [--collapsed file] script [args...]");
+    eprintln!("       rslox --coverage out.info script [args...]");
+    eprintln!("       rslox --trace [--lines from-to] script [args...]");
+    eprintln!("       rslox fmt [--check] files...");
+    eprintln!("       rslox lint [--allow|--warn|--deny rule]... files...");
+    eprintln!("       rslox test [--coverage out.info] [dirs or files...]");
+    eprintln!("       rslox lsp");
+    eprintln!("       rslox debug script [args...]");
+    eprintln!("       rslox dap");
+    std::process::exit(64);
+}
+
+fn serve_lsp() {
+    let stdin = std::io::stdin();
+    let code = lsp::run(stdin.lock(), std::io::stdout());
+    std::process::exit(code);
 }
 
-fn run_file(path: String) {
-    let source = match std::fs::read_to_string(path) {
+fn read_file(path: &str) -> String {
+    match std::fs::read_to_string(path) {
         Ok(source) => source,
         Err(err) => {
-            println!("Failed to read file: {}", err);
+            eprintln!("Failed to read file: {}", err);
             std::process::exit(1);
         }
-    };
+    }
+}
+
+fn run_file(path: String, script_args: Vec<String>) {
+    let source = read_file(&path);
+
+    let mut interpreter = new_interpreter();
+    interpreter.script_path = Some(path);
+    interpreter.script_args = script_args;
 
-    run_source(source);
+    exit_with_status(run_source(source, &mut interpreter));
+}
 
+/// Exits the way a script run ends: 65 for a syntax error, the status passed to
+/// `exit`, 70 for a runtime error, and otherwise returns.
+fn exit_with_status(exit_code: Option<i32>) {
     if error::had_error() {
         std::process::exit(65);
     }
 
+    if let Some(code) = exit_code {
+        std::process::exit(code);
+    }
+
     if error::had_runtime_error() {
         std::process::exit(70);
     }
 }
 
-fn run_prompt() {
-    loop {
-        print!("> ");
+fn serve_dap() {
+    let stdin = std::io::stdin();
+    let code = debugger::dap::run(stdin.lock(), std::io::stdout(), new_interpreter());
+    std::process::exit(code);
+}
 
-        match std::io::stdout().flush() {
-            Ok(_) => {}
-            Err(err) => {
-                println!("Failed to flush stdout: {}", err);
-                std::process::exit(1);
-            }
+/// Runs a script under the console debugger, reading commands from stdin.
+fn debug_file(path: String, script_args: Vec<String>) {
+    let source = read_file(&path);
+    let stdin = std::io::stdin();
+    let prompt = stdin.is_terminal();
+
+    let mut interpreter = new_interpreter();
+    interpreter.script_path = Some(path);
+    interpreter.script_args = script_args;
+    interpreter.debugger = Some(Box::new(Console::new(
+        &source,
+        Box::new(stdin.lock()),
+        Box::new(std::io::stdout()),
+        prompt,
+    )));
+
+    exit_with_status(run_source(source, &mut interpreter));
+}
+
+/// Runs a script under the profiler, then prints the per-line and per-function
+/// tables to stderr and writes the collapsed stacks if asked.
+fn profile_file(args: &[String]) {
+    let (collapsed, args) = option(args, "--collapsed");
+
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let source = read_file(path);
+    let profiler = Rc::new(RefCell::new(Profiler::new(path)));
+
+    let mut interpreter = new_interpreter();
+    interpreter.script_path = Some(path.clone());
+    interpreter.script_args = args[1..].to_vec();
+    interpreter.instrument = Some(Box::new(profiler.clone()));
+
+    let exit_code = run_source(source, &mut interpreter);
+    if error::had_error() {
+        std::process::exit(65);
+    }
+
+    let profiler = profiler.borrow();
+    if let Err(err) = profiler.report(&mut std::io::stderr()) {
+        eprintln!("Failed to write profile: {}", err);
+        std::process::exit(1);
+    }
+
+    if let Some(file) = collapsed {
+        if let Err(err) = std::fs::write(file, profiler.collapsed()) {
+            eprintln!("Failed to write file: {}", err);
+            std::process::exit(1);
         }
+    }
 
-        let mut line = String::new();
-        match std::io::stdin().read_line(&mut line) {
-            Ok(_) => {}
-            Err(err) => {
-                println!("Failed to read line: {}", err);
-                std::process::exit(1);
-            }
+    exit_with_status(exit_code);
+}
+
+/// Runs a script while counting the lines and branches it runs, then writes
+/// them to `file` as LCOV.
+fn cover_file(file: &str, args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let tokens = Scanner::new(read_file(path)).scan_tokens();
+    let statements = Parser::new(tokens).parse();
+    if error::had_error() {
+        std::process::exit(65);
+    }
+
+    let coverage = Rc::new(RefCell::new(Coverage::new(path, &statements)));
+
+    let mut interpreter = new_interpreter();
+    interpreter.script_path = Some(path.clone());
+    interpreter.script_args = args[1..].to_vec();
+    interpreter.instrument = Some(Box::new(coverage.clone()));
+
+    let exit_code = interpreter.interpret(&statements);
+    write_coverage(file, &[coverage]);
+
+    exit_with_status(exit_code);
+}
+
+/// Runs a script while logging each statement and expression value to stderr,
+/// leaving stdout to what the script prints.
+fn trace_file(args: &[String]) {
+    let (lines, args) = option(args, "--lines");
+    let lines = match lines.map(|lines| parse_line_range(lines)) {
+        Some(Some(lines)) => lines,
+        Some(None) => usage(),
+        None => 1..=usize::MAX,
+    };
+
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let source = read_file(path);
+
+    let mut interpreter = new_interpreter();
+    interpreter.script_path = Some(path.clone());
+    interpreter.script_args = args[1..].to_vec();
+    interpreter.instrument = Some(Box::new(Tracer::new(Box::new(std::io::stderr()), lines)));
+
+    exit_with_status(run_source(source, &mut interpreter));
+}
+
+/// Reads `from-to`, either end of which may be left out, or a single line. A
+/// range that ends before it starts is rejected rather than tracing nothing.
+fn parse_line_range(lines: &str) -> Option<RangeInclusive<usize>> {
+    let (from, to) = lines.split_once('-').unwrap_or((lines, lines));
+    let from = match from {
+        "" => 1,
+        from => from.parse().ok()?,
+    };
+    let to = match to {
+        "" => usize::MAX,
+        to => to.parse().ok()?,
+    };
+
+    (from <= to).then_some(from..=to)
+}
+
+fn write_coverage(file: &str, records: &[Rc<RefCell<Coverage>>]) {
+    let result = std::fs::File::create(file).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        for coverage in records {
+            coverage.borrow().write_lcov(&mut out)?;
         }
+        out.flush()
+    });
 
-        run_source(line);
+    if let Err(err) = result {
+        eprintln!("Failed to write file: {}", err);
+        std::process::exit(1);
+    }
+}
 
-        error::reset_error();
+/// Splits a leading `flag value` pair off `args`.
+fn option<'a>(args: &'a [String], flag: &str) -> (Option<&'a String>, &'a [String]) {
+    match args.first() {
+        Some(arg) if arg == flag => match args.get(1) {
+            Some(value) => (Some(value), &args[2..]),
+            None => usage(),
+        },
+        _ => (None, args),
     }
 }
 
-fn run_source(source: String) {
+/// Runs `source` and returns the status the script passed to `exit`, if any.
+fn run_source(source: String, interpreter: &mut Interpreter) -> Option<i32> {
     let mut scanner = Scanner::new(source);
     let tokens = scanner.scan_tokens();
     let mut parser = Parser::new(tokens);
     let expression = parser.parse();
 
     if error::had_error() {
-        return;
+        return None;
+    }
+
+    interpreter.interpret(&expression)
+}
+
+fn dump_tokens_file(path: &str) {
+    if !dump_tokens(read_file(path), &mut std::io::stdout()) {
+        std::process::exit(65);
+    }
+}
+
+/// Writes each token with its position to `out`, with scanner errors
+/// interleaved where they occurred. Returns whether scanning succeeded.
+fn dump_tokens(source: String, out: &mut dyn Write) -> bool {
+    let (tokens, errors) = Scanner::new(source).scan_tokens_with_errors();
+    let success = errors.is_empty();
+    let mut errors = errors.into_iter().peekable();
+
+    for token in &tokens {
+        while let Some(err) =
+            errors.next_if(|err| (err.line, err.column) <= (token.line, token.column))
+        {
+            let _ = writeln!(
+                out,
+                "{:>4}:{:<4} Error: {}",
+                err.line, err.column, err.message
+            );
+        }
+
+        let _ = writeln!(out, "{:>4}:{:<4} {}", token.line, token.column, token);
+    }
+
+    success
+}
+
+fn print_ast_file(path: &str) {
+    let tokens = Scanner::new(read_file(path)).scan_tokens();
+    let statements = Parser::new(tokens).parse();
+
+    if error::had_error() {
+        std::process::exit(65);
+    }
+
+    if !statements.is_empty() {
+        println!("{}", AstPrinter::new().print(&statements));
+    }
+}
+
+fn print_json_file(path: &str) {
+    let tokens = Scanner::new(read_file(path)).scan_tokens();
+    let statements = Parser::new(tokens).parse();
+
+    if error::had_error() {
+        std::process::exit(65);
+    }
+
+    match json::to_json(&statements) {
+        Ok(json) => println!("{}", json),
+        Err(err) => {
+            eprintln!("Failed to serialize AST: {}", err);
+            std::process::exit(70);
+        }
+    }
+}
+
+fn run_json_file(path: &str) {
+    let statements = match json::from_json(&read_file(path)) {
+        Ok(statements) => statements,
+        Err(err) => {
+            eprintln!("Invalid AST: {}", err);
+            std::process::exit(65);
+        }
+    };
+
+    if let Some(code) = new_interpreter().interpret(&statements) {
+        std::process::exit(code);
+    }
+
+    if error::had_runtime_error() {
+        std::process::exit(70);
+    }
+}
+
+/// Formats each file in place, or with `--check` only reports the files that
+/// would change and fails if there are any.
+fn format_files(args: &[String]) {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+
+    if paths.is_empty() {
+        usage();
+    }
+
+    let mut unformatted = false;
+
+    for path in paths {
+        let source = read_file(path);
+
+        let formatted = match formatter::format(source.clone()) {
+            Some(formatted) => formatted,
+            None => std::process::exit(65),
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("Would reformat {}", path);
+            unformatted = true;
+        } else if let Err(err) = std::fs::write(path, formatted) {
+            eprintln!("Failed to write file: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    if unformatted {
+        std::process::exit(1);
+    }
+}
+
+/// Lints each file, failing if any diagnostic has error severity.
+fn lint_files(args: &[String]) {
+    let mut config = LintConfig::new();
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let severity = match arg.as_str() {
+            "--allow" => Severity::Allow,
+            "--warn" => Severity::Warning,
+            "--deny" => Severity::Error,
+            _ => {
+                paths.push(arg);
+                continue;
+            }
+        };
+
+        let rule = match args.next() {
+            Some(rule) => rule,
+            None => usage(),
+        };
+
+        if let Err(rule) = config.set(rule, severity) {
+            eprintln!("Unknown lint rule '{}'. Known rules:", rule);
+            for rule in linter::RULES {
+                eprintln!("  {:<24}{}", rule.id, rule.description);
+            }
+            std::process::exit(64);
+        }
+    }
+
+    if paths.is_empty() {
+        usage();
+    }
+
+    let mut failed = false;
+
+    for path in paths {
+        let diagnostics = match linter::lint(read_file(path), &config) {
+            Some(diagnostics) => diagnostics,
+            None => std::process::exit(65),
+        };
+
+        for diagnostic in diagnostics {
+            println!("{}:{}", path, diagnostic);
+            failed |= diagnostic.severity == Severity::Error;
+        }
+
+        error::reset_error();
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Runs the tests in every `*_test.lox` file under the given paths, or under
+/// the current directory, failing if any test fails.
+fn test_files(args: &[String]) {
+    let (coverage_file, args) = option(args, "--coverage");
+    let mut records = Vec::new();
+
+    let roots = if args.is_empty() {
+        vec![String::from(".")]
+    } else {
+        args.to_vec()
+    };
+
+    let mut files = Vec::new();
+    for root in &roots {
+        match testing::discover(Path::new(root)) {
+            Ok(found) => files.extend(found),
+            Err(err) => {
+                eprintln!("Failed to read directory {}: {}", root, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("No *{} files found.", testing::SUFFIX);
+        std::process::exit(1);
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+
+    for file in files {
+        let path = file.display().to_string();
+        println!("{}", path);
+
+        let tokens = Scanner::new(read_file(&path)).scan_tokens();
+        let statements = Parser::new(tokens).parse();
+
+        if error::had_error() {
+            println!("  FAIL  {} does not parse", path);
+            failed += 1;
+            error::reset_error();
+            continue;
+        }
+
+        let coverage = coverage_file.map(|_| {
+            let coverage = Rc::new(RefCell::new(Coverage::new(&path, &statements)));
+            records.push(coverage.clone());
+            coverage
+        });
+        let mut interpreter = new_interpreter();
+        if let Some(coverage) = coverage {
+            interpreter.instrument = Some(Box::new(coverage));
+        }
+
+        for (name, result) in testing::run(&statements, interpreter) {
+            match result {
+                Ok(()) => {
+                    println!("  ok    {}", name);
+                    passed += 1;
+                }
+                Err(err) => {
+                    println!(
+                        "  FAIL  {} (line {}): {}",
+                        name, err.token.line, err.message
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed, {} failed", passed, failed);
+
+    if let Some(file) = coverage_file {
+        write_coverage(file, &records);
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
     }
+}
+
+fn new_interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+
+    if let Some(clock) = fixed_clock() {
+        interpreter.set_clock(Rc::new(clock));
+    }
+
+    interpreter
+}
+
+/// Honours `SOURCE_DATE_EPOCH` so that scripts reading the time can be run
+/// reproducibly; local time is then taken to be UTC.
+fn fixed_clock() -> Option<FixedClock> {
+    let epoch = std::env::var("SOURCE_DATE_EPOCH").ok()?;
 
-    Interpreter::new().interpret(&expression);
+    Some(FixedClock {
+        timestamp: epoch.trim().parse().ok()?,
+        offset: 0,
+    })
 }
@@ -0,0 +1,210 @@
+use crate::value::Value;
+
+/// Caps on the work a script may do, for running code that is not trusted.
+/// Each is off when `None`. Steps and memory are counted afresh for every
+/// `Lox::eval`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// How many statements and expressions may be evaluated.
+    pub steps: Option<u64>,
+    /// How deeply statements and expressions may nest while running. The
+    /// parser rejects anything nested past `MAX_NESTING` regardless.
+    pub depth: Option<usize>,
+    /// How many bytes new strings, lists and maps may take up in total.
+    pub memory: Option<usize>,
+}
+
+/// The limit a script ran into, carried by `ErrorKind::Limit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps,
+    Depth,
+    Memory,
+}
+
+/// Roughly how many bytes `value` occupies beyond its own slot: the text of a
+/// string, and the elements, keys and contents of a collection.
+pub fn size_of(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::List(list) => list
+            .borrow()
+            .iter()
+            .map(|element| std::mem::size_of::<Value>() + size_of(element))
+            .sum(),
+        Value::Map(map) => map
+            .borrow()
+            .iter()
+            .map(|(key, value)| key.len() + std::mem::size_of::<Value>() + size_of(value))
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::parser::MAX_NESTING,
+        error::{ErrorKind, LoxError},
+        lox::Lox,
+    };
+
+    fn limited(limits: Limits) -> Lox {
+        let mut lox = Lox::new();
+        lox.set_limits(limits);
+        lox.set_output(std::io::sink());
+        lox
+    }
+
+    fn limit_hit(result: Result<Value, LoxError>) -> Option<Limit> {
+        match result {
+            Err(LoxError::Runtime(err)) => match err.kind {
+                ErrorKind::Limit(limit) => Some(limit),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn parens(depth: usize) -> String {
+        format!("{}1{}", "(".repeat(depth), ")".repeat(depth))
+    }
+
+    #[test]
+    fn steps_are_counted_per_eval() {
+        let mut lox = limited(Limits {
+            steps: Some(10),
+            ..Limits::default()
+        });
+
+        // A statement is a step and so is each expression in it.
+        let five = "print 1; print 2; print 3; print 4; print 5;";
+        assert!(lox.eval(five).is_ok());
+        assert!(lox.eval(five).is_ok());
+        assert_eq!(
+            limit_hit(lox.eval(&format!("{} print 6;", five))),
+            Some(Limit::Steps)
+        );
+        assert!(lox.eval("1").is_ok());
+    }
+
+    #[test]
+    fn depth_stops_deep_nesting() {
+        let mut lox = limited(Limits {
+            depth: Some(10),
+            ..Limits::default()
+        });
+
+        // A lone expression nests one level per parenthesis plus the literal.
+        assert_eq!(lox.eval(&parens(9)).unwrap(), Value::Number(1.0));
+        assert_eq!(limit_hit(lox.eval(&parens(10))), Some(Limit::Depth));
+        assert_eq!(
+            limit_hit(lox.eval("{{{{{{{{{{ print 1; }}}}}}}}}}")),
+            Some(Limit::Depth)
+        );
+    }
+
+    fn too_deep(result: Result<Value, LoxError>) -> bool {
+        match result {
+            Err(LoxError::Parse(errors)) => errors[0].message == "Too much nesting.",
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn the_parser_allows_the_default_nesting() {
+        let mut lox = Lox::new();
+
+        assert_eq!(
+            lox.eval(&parens(MAX_NESTING - 1)).unwrap(),
+            Value::Number(1.0)
+        );
+        assert!(too_deep(lox.eval(&parens(MAX_NESTING))));
+    }
+
+    #[test]
+    fn a_higher_depth_limit_leaves_the_parser_bound_alone() {
+        let mut lox = limited(Limits {
+            depth: Some(1000),
+            ..Limits::default()
+        });
+
+        assert!(too_deep(lox.eval(&parens(MAX_NESTING))));
+    }
+
+    #[test]
+    fn long_chains_count_against_the_nesting() {
+        let mut lox = limited(Limits {
+            depth: Some(100),
+            steps: Some(10_000),
+            ..Limits::default()
+        });
+
+        let sum = format!("{}1", "1+".repeat(MAX_NESTING - 2));
+        assert_eq!(
+            lox.eval(&sum).unwrap(),
+            Value::Number(MAX_NESTING as f64 - 1.0)
+        );
+
+        assert!(too_deep(lox.eval(&format!("{}1", "1+".repeat(20_000)))));
+        assert!(too_deep(lox.eval(&format!("{}1", "1 or ".repeat(20_000)))));
+        assert!(too_deep(lox.eval(&format!("clock{}", "()".repeat(20_000)))));
+        assert!(too_deep(
+            lox.eval(&format!("var a = 1; {}1;", "a = ".repeat(20_000)))
+        ));
+        // Short chains nested inside each other add up.
+        assert!(too_deep(lox.eval(&format!(
+            "{}1{}",
+            "(".repeat(20),
+            (" + 1".repeat(20) + ")").repeat(20)
+        ))));
+    }
+
+    #[test]
+    fn memory_is_charged_for_new_strings() {
+        let mut lox = limited(Limits {
+            memory: Some(10),
+            ..Limits::default()
+        });
+
+        assert!(lox.eval("\"abcde\" + \"fghij\"").is_ok());
+        assert_eq!(
+            limit_hit(lox.eval("\"abcde\" + \"fghijk\"")),
+            Some(Limit::Memory)
+        );
+        assert_eq!(
+            limit_hit(lox.eval("var a = \"abcd\" + \"efgh\"; var b = a + \"ij\";")),
+            Some(Limit::Memory)
+        );
+        assert!(lox.eval("\"abc\" + \"def\"").is_ok());
+    }
+
+    #[test]
+    fn memory_is_charged_for_native_results() {
+        let mut lox = limited(Limits {
+            memory: Some(64),
+            ..Limits::default()
+        });
+
+        assert_eq!(
+            limit_hit(lox.eval("split(\",\", \"a,b,c,d,e,f\")")),
+            Some(Limit::Memory)
+        );
+    }
+
+    #[test]
+    fn sizes_count_contents() {
+        let slot = std::mem::size_of::<Value>();
+
+        assert_eq!(size_of(&Value::Number(1.0)), 0);
+        assert_eq!(size_of(&Value::String(String::from("héllo"))), 6);
+        assert_eq!(
+            size_of(&Value::list(vec![
+                Value::Nil,
+                Value::String(String::from("ab"))
+            ])),
+            2 * slot + 2
+        );
+    }
+}
@@ -0,0 +1,656 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::{
+        expr::{
+            AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, LiteralExpr, LogicalExpr,
+            UnaryExpr, VariableExpr,
+        },
+        parser::Parser,
+        statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, Stmt, TestStmt, VarStmt},
+    },
+    error,
+    expr_visitor::ExprVisitor,
+    interpreter::is_truthy,
+    lexer::{scanner::Scanner, token::Token, token_type::TokenType},
+    native,
+    stmt_visitor::StmtVisitor,
+    value::Value,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Allow,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Allow => write!(f, "allow"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "unused-variable",
+        severity: Severity::Warning,
+        description: "a variable is declared but never read",
+    },
+    Rule {
+        id: "undeclared-assignment",
+        severity: Severity::Error,
+        description: "an assignment targets a variable that was never declared",
+    },
+    Rule {
+        id: "shadowed-variable",
+        severity: Severity::Warning,
+        description: "a variable in a block hides one from an enclosing scope",
+    },
+    Rule {
+        id: "constant-condition",
+        severity: Severity::Warning,
+        description: "an if condition always has the same truth value",
+    },
+    Rule {
+        id: "mixed-type-comparison",
+        severity: Severity::Warning,
+        description: "literals of different types are compared",
+    },
+    Rule {
+        id: "unreachable-code",
+        severity: Severity::Warning,
+        description: "a statement can never run because the script has exited",
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.line, self.column, self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// Severity overrides for individual rules, e.g. from `--allow` or `--deny`.
+#[derive(Default)]
+pub struct LintConfig {
+    overrides: HashMap<&'static str, Severity>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails with the unknown id if `rule` does not name a rule.
+    pub fn set(&mut self, rule: &str, severity: Severity) -> Result<(), String> {
+        match RULES.iter().find(|r| r.id == rule) {
+            Some(r) => {
+                self.overrides.insert(r.id, severity);
+                Ok(())
+            }
+            None => Err(String::from(rule)),
+        }
+    }
+
+    fn severity(&self, rule: &Rule) -> Severity {
+        *self.overrides.get(rule.id).unwrap_or(&rule.severity)
+    }
+}
+
+/// Checks `source` without running it. Returns `None` after reporting the errors
+/// if `source` does not parse.
+///
+/// A `// lint:allow rule-id, ...` comment suppresses the rules on its own line
+/// or, when it stands alone, on the next line of code; `// lint:allow-file
+/// rule-id, ...` suppresses them for the whole file.
+pub fn lint(source: String, config: &LintConfig) -> Option<Vec<Diagnostic>> {
+    let (tokens, scan_errors) = Scanner::with_comments(source).scan_tokens_with_errors();
+    let suppressions = Suppressions::from_tokens(&tokens);
+
+    let code = tokens
+        .into_iter()
+        .filter(|token| token.token_type != TokenType::Comment)
+        .collect();
+    let (statements, parse_errors) = Parser::new(code).parse_with_errors();
+
+    if error::report_syntax_errors(&scan_errors, &parse_errors) {
+        return None;
+    }
+
+    let mut linter = Linter::new();
+    linter.lint_statements(&statements);
+    linter.end_scope();
+
+    let mut diagnostics: Vec<Diagnostic> = linter
+        .findings
+        .into_iter()
+        .filter_map(|(rule, token, message)| {
+            let rule = RULES.iter().find(|r| r.id == rule)?;
+            let severity = config.severity(rule);
+
+            if severity == Severity::Allow || suppressions.suppresses(rule.id, token.line) {
+                return None;
+            }
+
+            Some(Diagnostic {
+                rule: rule.id,
+                severity,
+                line: token.line,
+                column: token.column,
+                message,
+            })
+        })
+        .collect();
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    Some(diagnostics)
+}
+
+#[derive(Default)]
+struct Suppressions {
+    file: HashSet<String>,
+    lines: HashMap<usize, HashSet<String>>,
+}
+
+impl Suppressions {
+    fn from_tokens(tokens: &[Token]) -> Self {
+        let mut suppressions = Self::default();
+        let mut last_code_line = 0;
+
+        for (i, token) in tokens.iter().enumerate() {
+            if token.token_type != TokenType::Comment {
+                last_code_line = token.line;
+                continue;
+            }
+
+            let text = match token.lexeme.strip_prefix("//") {
+                Some(text) => text.trim(),
+                None => continue,
+            };
+
+            if let Some(rules) = text.strip_prefix("lint:allow-file") {
+                suppressions.file.extend(parse_rules(rules));
+            } else if let Some(rules) = text.strip_prefix("lint:allow") {
+                let line = if last_code_line == token.line {
+                    token.line
+                } else {
+                    tokens[i..]
+                        .iter()
+                        .find(|token| token.token_type != TokenType::Comment)
+                        .map_or(token.line, |token| token.line)
+                };
+
+                suppressions
+                    .lines
+                    .entry(line)
+                    .or_default()
+                    .extend(parse_rules(rules));
+            }
+        }
+
+        suppressions
+    }
+
+    fn suppresses(&self, rule: &str, line: usize) -> bool {
+        self.file.contains(rule)
+            || self
+                .lines
+                .get(&line)
+                .is_some_and(|rules| rules.contains(rule))
+    }
+}
+
+fn parse_rules(rules: &str) -> impl Iterator<Item = String> + '_ {
+    rules
+        .split(',')
+        .map(|rule| String::from(rule.trim()))
+        .filter(|rule| !rule.is_empty())
+}
+
+struct Variable {
+    name: Token,
+    read: bool,
+}
+
+struct Linter {
+    scopes: Vec<HashMap<String, Variable>>,
+    natives: HashSet<String>,
+    findings: Vec<(&'static str, Token, String)>,
+}
+
+impl Linter {
+    fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            natives: native::names().map(String::from).collect(),
+            findings: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, rule: &'static str, token: &Token, message: String) {
+        self.findings.push((rule, token.clone(), message));
+    }
+
+    fn lint_statements(&mut self, statements: &[Stmt]) {
+        let mut exited = false;
+
+        for stmt in statements {
+            if exited {
+                let token = stmt.first_token().clone();
+                self.report(
+                    "unreachable-code",
+                    &token,
+                    String::from("Unreachable statement after 'exit'."),
+                );
+                exited = false;
+            }
+
+            stmt.accept(self);
+
+            if self.terminates(stmt) {
+                exited = true;
+            }
+        }
+    }
+
+    fn lint_expr(&mut self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn end_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            let mut unused: Vec<Variable> = scope
+                .into_values()
+                .filter(|variable| !variable.read)
+                .collect();
+            unused.sort_by_key(|variable| (variable.name.line, variable.name.column));
+
+            for variable in unused {
+                self.report_unused(&variable.name);
+            }
+        }
+    }
+
+    fn report_unused(&mut self, name: &Token) {
+        self.report(
+            "unused-variable",
+            name,
+            format!("Variable '{}' is never read.", name.lexeme),
+        );
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(name))
+    }
+
+    /// Whether `stmt` always ends the script through the `exit` native.
+    fn terminates(&self, stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Expression(stmt) => match &stmt.expression {
+                Expr::Call(call) => match call.callee.as_ref() {
+                    Expr::Variable(callee) => {
+                        callee.name.lexeme == "exit" && !self.is_declared("exit")
+                    }
+                    _ => false,
+                },
+                _ => false,
+            },
+            Stmt::Block(stmt) => stmt.statements.iter().any(|stmt| self.terminates(stmt)),
+            Stmt::If(stmt) => match &stmt.else_branch {
+                Some(else_branch) => {
+                    self.terminates(&stmt.then_branch) && self.terminates(else_branch)
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl StmtVisitor<()> for Linter {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.scopes.push(HashMap::new());
+        self.lint_statements(&stmt.statements);
+        self.end_scope();
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        self.lint_expr(&stmt.expression);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        if let Some(value) = constant_value(&stmt.condition) {
+            self.report(
+                "constant-condition",
+                &stmt.keyword,
+                format!("Condition is always {}.", is_truthy(&value)),
+            );
+        }
+
+        self.lint_expr(&stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        self.lint_expr(&stmt.expression);
+    }
+
+    fn visit_test_stmt(&mut self, stmt: &TestStmt) {
+        stmt.body.accept(self);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        if let Some(initializer) = &stmt.initializer {
+            self.lint_expr(initializer);
+        }
+
+        let name = stmt.name.lexeme.clone();
+        let depth = self.scopes.len();
+
+        if depth > 1
+            && self.scopes[..depth - 1]
+                .iter()
+                .any(|s| s.contains_key(&name))
+        {
+            self.report(
+                "shadowed-variable",
+                &stmt.name,
+                format!("Variable '{}' shadows a variable in an outer scope.", name),
+            );
+        }
+
+        let variable = Variable {
+            name: stmt.name.clone(),
+            read: false,
+        };
+
+        if let Some(previous) = self.scopes[depth - 1].insert(name, variable) {
+            if !previous.read {
+                self.report_unused(&previous.name);
+            }
+        }
+    }
+}
+
+impl ExprVisitor<()> for Linter {
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        self.lint_expr(&expr.value);
+
+        if !self.is_declared(&expr.name.lexeme) && !self.natives.contains(&expr.name.lexeme) {
+            self.report(
+                "undeclared-assignment",
+                &expr.name,
+                format!("Assignment to undeclared variable '{}'.", expr.name.lexeme),
+            );
+        }
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
+        let comparison = matches!(
+            expr.operator.token_type,
+            TokenType::EqualEqual
+                | TokenType::BangEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+        );
+
+        if comparison {
+            if let (Some(left), Some(right)) = (literal(&expr.left), literal(&expr.right)) {
+                if type_name(left) != type_name(right) {
+                    self.report(
+                        "mixed-type-comparison",
+                        &expr.operator,
+                        format!(
+                            "Comparison between a {} and a {}.",
+                            type_name(left),
+                            type_name(right)
+                        ),
+                    );
+                }
+            }
+        }
+
+        self.lint_expr(&expr.left);
+        self.lint_expr(&expr.right);
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) {
+        self.lint_expr(&expr.callee);
+        for argument in &expr.arguments {
+            self.lint_expr(argument);
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) {
+        self.lint_expr(&expr.expression);
+    }
+
+    fn visit_literal_expr(&mut self, _: &LiteralExpr) {}
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) {
+        self.lint_expr(&expr.left);
+        self.lint_expr(&expr.right);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        self.lint_expr(&expr.right);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(variable) = scope.get_mut(&expr.name.lexeme) {
+                variable.read = true;
+                return;
+            }
+        }
+    }
+}
+
+fn literal(expr: &Expr) -> Option<&Value> {
+    match expr {
+        Expr::Literal(expr) => Some(expr.value.as_ref().unwrap_or(&Value::Nil)),
+        Expr::Grouping(expr) => literal(&expr.expression),
+        _ => None,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Nil => "nil",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::List(_) => "list",
+        Value::Map(_) => "map",
+        Value::NativeFunction(_) | Value::HostFunction(_) => "function",
+    }
+}
+
+/// Folds expressions built only from literals, returning `None` if the value
+/// depends on anything else or would be a runtime error.
+fn constant_value(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Literal(expr) => Some(expr.value.clone().unwrap_or(Value::Nil)),
+        Expr::Grouping(expr) => constant_value(&expr.expression),
+        Expr::Unary(expr) => {
+            let right = constant_value(&expr.right)?;
+            match (&expr.operator.token_type, right) {
+                (TokenType::Bang, right) => Some(Value::Bool(!is_truthy(&right))),
+                (TokenType::Minus, Value::Number(n)) => Some(Value::Number(-n)),
+                _ => None,
+            }
+        }
+        Expr::Logical(expr) => {
+            let left = constant_value(&expr.left)?;
+            let short_circuits = match expr.operator.token_type {
+                TokenType::Or => is_truthy(&left),
+                _ => !is_truthy(&left),
+            };
+
+            if short_circuits {
+                Some(left)
+            } else {
+                constant_value(&expr.right)
+            }
+        }
+        Expr::Binary(expr) => {
+            let left = constant_value(&expr.left)?;
+            let right = constant_value(&expr.right)?;
+
+            match (&expr.operator.token_type, left, right) {
+                (TokenType::EqualEqual, l, r) => Some(Value::Bool(l == r)),
+                (TokenType::BangEqual, l, r) => Some(Value::Bool(l != r)),
+                (TokenType::Greater, Value::Number(l), Value::Number(r)) => {
+                    Some(Value::Bool(l > r))
+                }
+                (TokenType::GreaterEqual, Value::Number(l), Value::Number(r)) => {
+                    Some(Value::Bool(l >= r))
+                }
+                (TokenType::Less, Value::Number(l), Value::Number(r)) => Some(Value::Bool(l < r)),
+                (TokenType::LessEqual, Value::Number(l), Value::Number(r)) => {
+                    Some(Value::Bool(l <= r))
+                }
+                (TokenType::Plus, Value::Number(l), Value::Number(r)) => Some(Value::Number(l + r)),
+                (TokenType::Plus, Value::String(l), Value::String(r)) => {
+                    Some(Value::String(l + &r))
+                }
+                (TokenType::Minus, Value::Number(l), Value::Number(r)) => {
+                    Some(Value::Number(l - r))
+                }
+                (TokenType::Star, Value::Number(l), Value::Number(r)) => Some(Value::Number(l * r)),
+                (TokenType::Slash, Value::Number(l), Value::Number(r)) if r != 0.0 => {
+                    Some(Value::Number(l / r))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str, config: &LintConfig) -> Vec<(&'static str, Severity, usize)> {
+        lint(String::from(source), config)
+            .expect("source parses")
+            .into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.severity, diagnostic.line))
+            .collect()
+    }
+
+    fn default_rules(source: &str) -> Vec<(&'static str, usize)> {
+        rules(source, &LintConfig::new())
+            .into_iter()
+            .map(|(rule, _, line)| (rule, line))
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables() {
+        assert_eq!(
+            default_rules("var used = 1;\nprint used;\n{\n  var unused = 2;\n}"),
+            [("unused-variable", 4)]
+        );
+    }
+
+    #[test]
+    fn undeclared_assignments() {
+        assert_eq!(
+            default_rules("var x = 1;\nx = 2;\ny = 3;\nprint x;"),
+            [("undeclared-assignment", 3)]
+        );
+    }
+
+    #[test]
+    fn shadowed_variables() {
+        assert_eq!(
+            default_rules("var x = 1;\n{\n  var x = 2;\n  print x;\n}\nprint x;"),
+            [("shadowed-variable", 3)]
+        );
+    }
+
+    #[test]
+    fn constant_conditions() {
+        assert_eq!(
+            default_rules("if (1 < 2) { print 1; }\nif (clock() > 0) { print 2; }"),
+            [("constant-condition", 1)]
+        );
+    }
+
+    #[test]
+    fn mixed_type_comparisons() {
+        assert_eq!(
+            default_rules("print 1 == \"1\";\nprint 1 == 1;"),
+            [("mixed-type-comparison", 1)]
+        );
+    }
+
+    #[test]
+    fn unreachable_code() {
+        assert_eq!(
+            default_rules("print 1;\nexit(0);\nprint 2;"),
+            [("unreachable-code", 3)]
+        );
+    }
+
+    #[test]
+    fn suppression_comments() {
+        let source = "\
+// lint:allow-file shadowed-variable
+var a = 1; // lint:allow unused-variable
+// lint:allow unused-variable
+var b = 2;
+var c = 3;
+{ var c = 4; print c; }
+print c;";
+        assert_eq!(default_rules(source), []);
+    }
+
+    #[test]
+    fn severities_can_be_overridden() {
+        let mut config = LintConfig::new();
+        config.set("unused-variable", Severity::Error).unwrap();
+        config
+            .set("undeclared-assignment", Severity::Allow)
+            .unwrap();
+
+        assert_eq!(
+            rules("var x = 1;\ny = 2;", &config),
+            [("unused-variable", Severity::Error, 1)]
+        );
+        assert_eq!(
+            config.set("no-such-rule", Severity::Allow),
+            Err(String::from("no-such-rule"))
+        );
+    }
+}
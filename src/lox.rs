@@ -0,0 +1,420 @@
+use std::{io::Write, path::Path};
+
+use crate::{
+    ast::{
+        expr::Expr,
+        parser::Parser,
+        statement::{ExpressionStmt, Stmt},
+    },
+    convert::{FromArgs, IntoLox},
+    error::{LoxError, RuntimeError},
+    host::Context,
+    interpreter::Interpreter,
+    interrupt::InterruptHandle,
+    lexer::scanner::Scanner,
+    limits::Limits,
+    value::Value,
+};
+
+/// An interpreter session for embedding Lox in a Rust program. Globals persist
+/// across calls. Errors are returned, never printed, and nothing in here exits
+/// the process; a script calling `exit(code)` yields a runtime error of kind
+/// `ErrorKind::Exit`.
+///
+/// ```
+/// let mut lox = rslox::Lox::new();
+/// lox.eval("var answer = 6 * 7;").unwrap();
+/// assert_eq!(lox.eval("answer").unwrap(), rslox::Value::Number(42.0));
+/// ```
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self::with_interpreter(Interpreter::new())
+    }
+
+    /// A session around an interpreter set up by the caller, e.g. the REPL's.
+    pub(crate) fn with_interpreter(interpreter: Interpreter) -> Self {
+        Self { interpreter }
+    }
+
+    pub(crate) fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Runs `source` and returns the value of its last statement if that is an
+    /// expression statement, and `nil` otherwise. A lone expression needs no
+    /// trailing semicolon.
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError> {
+        self.eval_each(source, |_, _, _| Ok(()))
+    }
+
+    /// Runs `source` like `eval`, handing the value of each expression statement
+    /// to `each` as soon as it is computed.
+    pub(crate) fn eval_each(
+        &mut self,
+        source: &str,
+        mut each: impl FnMut(&mut Interpreter, &Expr, &Value) -> Result<(), RuntimeError>,
+    ) -> Result<Value, LoxError> {
+        // An interrupt left over from a previous snippet must not stop this one.
+        self.interpreter.interrupt_handle().clear();
+        let (tokens, scan_errors) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        if !scan_errors.is_empty() {
+            return Err(LoxError::Scan(scan_errors));
+        }
+
+        let statements = match Parser::new(tokens.clone()).parse_expression() {
+            Ok(expression) => vec![Stmt::Expression(ExpressionStmt::new(expression))],
+            Err(_) => {
+                let (statements, parse_errors) = Parser::new(tokens).parse_with_errors();
+                if !parse_errors.is_empty() {
+                    return Err(LoxError::Parse(parse_errors));
+                }
+                statements
+            }
+        };
+
+        self.interpreter.reset_usage();
+
+        let mut value = Value::Nil;
+        for stmt in &statements {
+            value = match stmt {
+                Stmt::Expression(stmt) => {
+                    let value = self.interpreter.evaluate(&stmt.expression)?;
+                    each(&mut self.interpreter, &stmt.expression, &value)?;
+                    value
+                }
+                stmt => {
+                    self.interpreter.execute(stmt)?;
+                    Value::Nil
+                }
+            };
+        }
+
+        Ok(value)
+    }
+
+    /// Reads and runs a script, which sees `path` through `scriptPath()`.
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<Value, LoxError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+
+        self.interpreter.script_path = Some(path.display().to_string());
+        self.eval(&source)
+    }
+
+    /// Makes `function` callable from scripts as `name`. Scripts calling it with
+    /// the wrong number of arguments get a runtime error if `arity` is given.
+    /// Errors built with `Context::error` point at the call in the script.
+    ///
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    /// use rslox::{Lox, Value};
+    ///
+    /// let calls = Rc::new(Cell::new(0));
+    /// let counter = calls.clone();
+    ///
+    /// let mut lox = Lox::new();
+    /// lox.register_fn("double", Some(1), move |ctx, args| {
+    ///     counter.set(counter.get() + 1);
+    ///     match args[0] {
+    ///         Value::Number(n) => Ok(Value::Number(n * 2.0)),
+    ///         _ => Err(ctx.error("Expected a number.")),
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(lox.eval("double(21)").unwrap(), Value::Number(42.0));
+    /// let err = lox.eval("double(nil)").unwrap_err();
+    /// assert_eq!(err.to_string(), "[line 1] Error at ')': Expected a number.");
+    /// assert_eq!(calls.get(), 2);
+    /// ```
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        arity: Option<usize>,
+        function: impl Fn(&mut Context, &[Value]) -> Result<Value, RuntimeError> + 'static,
+    ) {
+        self.interpreter
+            .define_host_function(name, arity, Box::new(function));
+    }
+
+    /// Like `register_fn`, but the arguments arrive converted to the types of
+    /// `f`'s tuple parameter and the result is converted back. The arity is the
+    /// length of the tuple. An argument that does not convert is reported at the
+    /// call, e.g. "Argument 2 to 'clamp' must be a number.".
+    ///
+    /// ```
+    /// use rslox::{Lox, Value};
+    ///
+    /// let mut lox = Lox::new();
+    /// lox.register_typed("repeat", |_, (text, times): (String, u32)| {
+    ///     Ok(text.repeat(times as usize))
+    /// });
+    ///
+    /// assert_eq!(lox.eval("repeat(\"ab\", 2)").unwrap(), Value::String("abab".into()));
+    /// let err = lox.eval("repeat(\"ab\", -1)").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "[line 1] Error at ')': Argument 2 to 'repeat' must be an integer between 0 and 4294967295."
+    /// );
+    /// ```
+    pub fn register_typed<A: FromArgs, R: IntoLox>(
+        &mut self,
+        name: &str,
+        function: impl Fn(&mut Context, A) -> Result<R, RuntimeError> + 'static,
+    ) {
+        let function_name = String::from(name);
+
+        self.register_fn(name, Some(A::ARITY), move |ctx, args| {
+            let args = A::from_args(args).map_err(|(index, err)| {
+                ctx.error(format!(
+                    "Argument {} to '{}' must be {}.",
+                    index + 1,
+                    function_name,
+                    err.expected
+                ))
+            })?;
+
+            function(ctx, args).map(IntoLox::into_lox)
+        });
+    }
+
+    /// Caps the steps, nesting depth and memory of each later `eval`. A script
+    /// that runs past one stops with a runtime error of kind `ErrorKind::Limit`,
+    /// and the session stays usable.
+    ///
+    /// ```
+    /// use rslox::{ErrorKind, Limit, Limits, Lox, LoxError};
+    ///
+    /// let mut lox = Lox::new();
+    /// lox.set_limits(Limits {
+    ///     steps: Some(100),
+    ///     ..Limits::default()
+    /// });
+    ///
+    /// let source = "1 + 1;\n".repeat(100);
+    /// match lox.eval(&source) {
+    ///     Err(LoxError::Runtime(err)) => assert_eq!(err.kind, ErrorKind::Limit(Limit::Steps)),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.limits = limits;
+    }
+
+    /// A handle that stops the running `eval` from another thread with a runtime
+    /// error of kind `ErrorKind::Interrupted`. The session stays usable.
+    ///
+    /// ```
+    /// use rslox::{ErrorKind, Lox, LoxError};
+    /// use std::{sync::mpsc, thread, time::Duration};
+    ///
+    /// let mut lox = Lox::new();
+    /// let (started, running) = mpsc::channel();
+    /// lox.register_fn("work", Some(0), move |ctx, _| {
+    ///     started.send(()).unwrap();
+    ///     loop {
+    ///         ctx.check_interrupt()?;
+    ///         thread::sleep(Duration::from_millis(1));
+    ///     }
+    /// });
+    ///
+    /// let handle = lox.interrupt_handle();
+    /// let stopper = thread::spawn(move || {
+    ///     running.recv().unwrap();
+    ///     handle.interrupt();
+    /// });
+    ///
+    /// match lox.eval("work();") {
+    ///     Err(LoxError::Runtime(err)) => assert_eq!(err.kind, ErrorKind::Interrupted),
+    ///     _ => unreachable!(),
+    /// }
+    /// stopper.join().unwrap();
+    /// assert!(lox.eval("1 + 1").is_ok());
+    /// ```
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interpreter.interrupt_handle()
+    }
+
+    /// Sends what scripts `print` to `output` instead of stdout.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.interpreter.output = Box::new(output);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter.environment.lookup(name).cloned()
+    }
+
+    /// Defines or replaces a global variable.
+    pub fn set_global(&mut self, name: &str, value: impl IntoLox) {
+        self.interpreter
+            .environment
+            .define(String::from(name), value.into_lox());
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{capture::Capture, error::ErrorKind};
+
+    #[test]
+    fn globals_persist_between_evals() {
+        let mut lox = Lox::new();
+
+        assert_eq!(lox.eval("var a = 1;").unwrap(), Value::Nil);
+        assert_eq!(lox.eval("a = a + 1; a * 10;").unwrap(), Value::Number(20.0));
+        assert_eq!(lox.get_global("a"), Some(Value::Number(2.0)));
+        assert_eq!(lox.get_global("missing"), None);
+
+        lox.set_global("name", "lox");
+        assert_eq!(
+            lox.eval("\"hello \" + name").unwrap(),
+            Value::String(String::from("hello lox"))
+        );
+    }
+
+    #[test]
+    fn scan_errors_are_returned() {
+        let mut lox = Lox::new();
+
+        match lox.eval("var a = 1;\n@ # \"open") {
+            Err(LoxError::Scan(errors)) => {
+                let errors: Vec<_> = errors
+                    .iter()
+                    .map(|err| (err.line, err.column, err.message.as_str()))
+                    .collect();
+                assert_eq!(
+                    errors,
+                    [
+                        (2, 1, "Unexpected character."),
+                        (2, 3, "Unexpected character."),
+                        (2, 5, "Unterminated string."),
+                    ]
+                );
+            }
+            _ => panic!("expected scan errors"),
+        }
+        assert_eq!(lox.get_global("a"), None);
+    }
+
+    #[test]
+    fn parse_errors_are_returned() {
+        let mut lox = Lox::new();
+
+        let err = lox.eval("print 1;\nprint ;\nprint (1 + 2;").unwrap_err();
+        assert!(matches!(&err, LoxError::Parse(errors) if errors.len() == 2));
+        assert_eq!(
+            err.to_string(),
+            "[line 2] Error at ';': Expected expression.\n\
+             [line 3] Error at ';': Expected ')' after expression."
+        );
+    }
+
+    #[test]
+    fn runtime_errors_carry_the_line() {
+        let mut lox = Lox::new();
+
+        match lox.eval("var a = 1;\nprint a + nil;") {
+            Err(LoxError::Runtime(err)) => {
+                assert_eq!(err.kind, ErrorKind::Error);
+                assert_eq!(err.token.line, 2);
+                assert_eq!(
+                    err.to_string(),
+                    "[line 2] Error at '+': Operands must be two numbers or two strings."
+                );
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        assert_eq!(lox.eval("a").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn exit_is_an_error_not_a_process_exit() {
+        let mut lox = Lox::new();
+
+        match lox.eval("exit(4); print 1;") {
+            Err(LoxError::Runtime(err)) => assert_eq!(err.kind, ErrorKind::Exit(4)),
+            _ => panic!("expected an exit"),
+        }
+        assert!(lox.eval("1").is_ok());
+    }
+
+    #[test]
+    fn errors_are_returned_not_reported() {
+        let reports = Capture::new();
+        let previous = crate::error::set_reporter(Box::new(reports.clone()));
+
+        let mut lox = Lox::new();
+        assert!(lox.eval("@").is_err());
+        assert!(lox.eval("print ;").is_err());
+        assert!(lox.eval("-nil").is_err());
+
+        crate::error::set_reporter(previous);
+        assert_eq!(reports.contents(), "");
+    }
+
+    #[test]
+    fn run_file_sets_the_script_path() {
+        let path = std::env::temp_dir().join(format!("rslox-lox-{}.lox", std::process::id()));
+        std::fs::write(&path, "scriptPath()").unwrap();
+
+        let mut lox = Lox::new();
+        let value = lox.run_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(value.unwrap(), Value::String(path.display().to_string()));
+        assert!(matches!(
+            lox.run_file("/nonexistent/script.lox"),
+            Err(LoxError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn print_goes_to_the_output() {
+        let output = Capture::new();
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+
+        lox.eval("print 1; print \"two\";").unwrap();
+        assert_eq!(output.contents(), "1\ntwo\n");
+    }
+
+    #[test]
+    fn an_interrupt_between_evals_does_not_stop_the_next() {
+        let output = Capture::new();
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+
+        lox.eval("print 1;").unwrap();
+        lox.interrupt_handle().interrupt();
+        lox.eval("print 2;").unwrap();
+        assert_eq!(output.contents(), "1\n2\n");
+    }
+
+    #[test]
+    fn an_interrupt_stops_the_expression_being_evaluated() {
+        let mut lox = Lox::new();
+        let handle = lox.interrupt_handle();
+        lox.register_fn("stop", Some(0), move |_, _| {
+            handle.interrupt();
+            Ok(Value::Nil)
+        });
+
+        match lox.eval("stop() == 1") {
+            Err(LoxError::Runtime(err)) => {
+                assert_eq!(err.kind, ErrorKind::Interrupted);
+                assert_eq!(err.token.line, 1);
+            }
+            _ => panic!("expected an interrupt"),
+        }
+    }
+}
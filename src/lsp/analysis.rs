@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        expr::{
+            AssignExpr, BinaryExpr, CallExpr, GroupingExpr, LiteralExpr, LogicalExpr, UnaryExpr,
+            VariableExpr,
+        },
+        parser::Parser,
+        statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, TestStmt, VarStmt},
+    },
+    expr_visitor::ExprVisitor,
+    lexer::{scanner::Scanner, token::Token},
+    stmt_visitor::StmtVisitor,
+};
+
+/// A problem found while scanning or parsing, at a 1-based position.
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+/// A declared variable and every place that refers to it.
+pub struct Symbol {
+    pub name: Token,
+    pub references: Vec<Token>,
+}
+
+/// What the language server knows about one document.
+pub struct Analysis {
+    pub tokens: Vec<Token>,
+    pub symbols: Vec<Symbol>,
+    pub problems: Vec<Problem>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let (tokens, scan_errors) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        let (statements, parse_errors) = Parser::new(tokens.clone()).parse_with_errors();
+
+        let mut problems: Vec<Problem> = scan_errors
+            .into_iter()
+            .map(|err| Problem {
+                line: err.line,
+                column: err.column,
+                length: 1,
+                message: err.message,
+            })
+            .collect();
+        problems.extend(parse_errors.into_iter().map(|err| Problem {
+            line: err.token.line,
+            column: err.token.column,
+            length: err.token.lexeme.chars().count().max(1),
+            message: err.message,
+        }));
+
+        let mut resolver = Resolver::new();
+        for stmt in &statements {
+            stmt.accept(&mut resolver);
+        }
+
+        Self {
+            tokens,
+            symbols: resolver.finish(),
+            problems,
+        }
+    }
+
+    /// The symbol declared or referenced by the token at a 1-based position.
+    pub fn symbol_at(&self, line: usize, column: usize) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| {
+            contains(&symbol.name, line, column)
+                || symbol
+                    .references
+                    .iter()
+                    .any(|token| contains(token, line, column))
+        })
+    }
+
+    /// The token at a 1-based position.
+    pub fn token_at(&self, line: usize, column: usize) -> Option<&Token> {
+        self.tokens
+            .iter()
+            .find(|token| contains(token, line, column))
+    }
+}
+
+fn contains(token: &Token, line: usize, column: usize) -> bool {
+    token.line == line
+        && token.column <= column
+        && column <= token.column + token.lexeme.chars().count()
+}
+
+/// Binds each variable use to its declaration, following the interpreter's
+/// block scoping. A use with no declaration in scope falls back to a global
+/// declared later in the file.
+struct Resolver {
+    symbols: Vec<Symbol>,
+    scopes: Vec<HashMap<String, usize>>,
+    unresolved: Vec<Token>,
+}
+
+impl Resolver {
+    fn new() -> Self {
+        Self {
+            symbols: Vec::new(),
+            scopes: vec![HashMap::new()],
+            unresolved: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> Vec<Symbol> {
+        for token in std::mem::take(&mut self.unresolved) {
+            if let Some(&index) = self.scopes[0].get(&token.lexeme) {
+                self.symbols[index].references.push(token);
+            }
+        }
+
+        self.symbols
+    }
+
+    fn reference(&mut self, token: &Token) {
+        let index = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&token.lexeme));
+
+        match index {
+            Some(&index) => self.symbols[index].references.push(token.clone()),
+            None => self.unresolved.push(token.clone()),
+        }
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.scopes.push(HashMap::new());
+        for statement in &stmt.statements {
+            statement.accept(self);
+        }
+        self.scopes.pop();
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_test_stmt(&mut self, stmt: &TestStmt) {
+        stmt.body.accept(self);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        if let Some(initializer) = &stmt.initializer {
+            initializer.accept(self);
+        }
+
+        self.symbols.push(Symbol {
+            name: stmt.name.clone(),
+            references: Vec::new(),
+        });
+
+        let index = self.symbols.len() - 1;
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(stmt.name.lexeme.clone(), index);
+        }
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        expr.value.accept(self);
+        self.reference(&expr.name);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) {
+        expr.callee.accept(self);
+        for argument in &expr.arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_literal_expr(&mut self, _: &LiteralExpr) {}
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        expr.right.accept(self);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) {
+        self.reference(&expr.name);
+    }
+}
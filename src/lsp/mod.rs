@@ -0,0 +1,557 @@
+mod analysis;
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value as Json};
+
+use crate::{
+    lexer::{scanner::KEYWORDS, token::Token},
+    native, protocol,
+};
+
+use analysis::Analysis;
+
+const SYMBOL_KIND_VARIABLE: u32 = 13;
+const COMPLETION_KIND_FUNCTION: u32 = 3;
+const COMPLETION_KIND_VARIABLE: u32 = 6;
+const COMPLETION_KIND_KEYWORD: u32 = 14;
+
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Serves the Language Server Protocol over `reader` and `writer` until the
+/// client sends `exit`. Returns the process status the protocol asks for: 0 if
+/// `shutdown` came first, 1 otherwise.
+pub fn run(mut reader: impl BufRead, writer: impl Write) -> i32 {
+    let mut server = Server::new(writer);
+
+    loop {
+        let message = match protocol::read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => return 1,
+            Err(err) => {
+                eprintln!("lsp: {}", err);
+                return 1;
+            }
+        };
+
+        if message["method"] == "exit" {
+            return if server.shut_down { 0 } else { 1 };
+        }
+
+        if let Err(err) = server.handle(&message) {
+            eprintln!("lsp: {}", err);
+            return 1;
+        }
+    }
+}
+
+struct Server<W: Write> {
+    writer: W,
+    documents: HashMap<String, String>,
+    shut_down: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            documents: HashMap::new(),
+            shut_down: false,
+        }
+    }
+
+    fn handle(&mut self, message: &Json) -> io::Result<()> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+
+        let id = match message.get("id") {
+            Some(id) => id,
+            None => return self.notification(method, params),
+        };
+
+        let result = match method {
+            "initialize" => Ok(initialize()),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unhandled method '{}'.", method))),
+        };
+
+        match result {
+            Ok(result) => self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            Err((code, message)) => self.send(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            })),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), String::from(text));
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole document.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(uri.clone(), String::from(text));
+                }
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.send(json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let source = self.documents.get(uri).cloned().unwrap_or_default();
+        let diagnostics: Vec<Json> = Analysis::new(&source)
+            .problems
+            .iter()
+            .map(|problem| {
+                json!({
+                    "range": range(&source, problem.line, problem.column, problem.length),
+                    "severity": 1,
+                    "source": "rslox",
+                    "message": problem.message,
+                })
+            })
+            .collect();
+
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, (i32, String)> {
+        let (uri, source, line, column) = self.position(params)?;
+        let analysis = Analysis::new(source);
+
+        Ok(match analysis.symbol_at(line, column) {
+            Some(symbol) => location(uri, source, &symbol.name),
+            None => Json::Null,
+        })
+    }
+
+    fn references(&self, params: &Json) -> Result<Json, (i32, String)> {
+        let (uri, source, line, column) = self.position(params)?;
+        let analysis = Analysis::new(source);
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+
+        let symbol = match analysis.symbol_at(line, column) {
+            Some(symbol) => symbol,
+            None => return Ok(Json::Null),
+        };
+
+        let declaration = include_declaration.then_some(&symbol.name);
+        Ok(declaration
+            .into_iter()
+            .chain(&symbol.references)
+            .map(|token| location(uri, source, token))
+            .collect())
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, (i32, String)> {
+        let (_, source, line, column) = self.position(params)?;
+        let analysis = Analysis::new(source);
+
+        let token = match analysis.token_at(line, column) {
+            Some(token) => token,
+            None => return Ok(Json::Null),
+        };
+
+        let contents = match analysis.symbol_at(line, column) {
+            Some(symbol) => {
+                let declaration = source.lines().nth(symbol.name.line - 1).unwrap_or("");
+                format!("```lox\n{}\n```", declaration.trim())
+            }
+            None => match native::natives().find(|native| native.name == token.lexeme) {
+                Some(native) => {
+                    let arity = match native.arity {
+                        Some(arity) => arity.to_string(),
+                        None => String::from("variadic"),
+                    };
+                    format!(
+                        "```lox\n<native fn {}>\n```\nBuilt-in function, arity {}.",
+                        native.name, arity
+                    )
+                }
+                None => return Ok(Json::Null),
+            },
+        };
+
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": range(source, token.line, token.column, token.lexeme.chars().count()),
+        }))
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, (i32, String)> {
+        let (uri, source) = self.document(params)?;
+
+        Ok(Analysis::new(source)
+            .symbols
+            .iter()
+            .map(|symbol| {
+                json!({
+                    "name": symbol.name.lexeme,
+                    "kind": SYMBOL_KIND_VARIABLE,
+                    "location": location(uri, source, &symbol.name),
+                })
+            })
+            .collect())
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, (i32, String)> {
+        let (_, source) = self.document(params)?;
+        let analysis = Analysis::new(source);
+
+        let mut keywords: Vec<&str> = KEYWORDS.keys().copied().collect();
+        keywords.sort_unstable();
+
+        let mut variables: Vec<&str> = analysis
+            .symbols
+            .iter()
+            .map(|symbol| symbol.name.lexeme.as_str())
+            .collect();
+        variables.sort_unstable();
+        variables.dedup();
+
+        let items = keywords
+            .into_iter()
+            .map(|name| (name, COMPLETION_KIND_KEYWORD))
+            .chain(
+                variables
+                    .into_iter()
+                    .map(|name| (name, COMPLETION_KIND_VARIABLE)),
+            )
+            .chain(native::names().map(|name| (name, COMPLETION_KIND_FUNCTION)))
+            .map(|(label, kind)| json!({ "label": label, "kind": kind }))
+            .collect::<Vec<_>>();
+
+        Ok(json!(items))
+    }
+
+    fn document<'a>(&'a self, params: &'a Json) -> Result<(&'a str, &'a str), (i32, String)> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, String::from("Missing textDocument.uri.")))?;
+
+        match self.documents.get(uri) {
+            Some(source) => Ok((uri, source)),
+            None => Err((INVALID_PARAMS, format!("Unknown document '{}'.", uri))),
+        }
+    }
+
+    /// The document and the 1-based line and column a request points at. The
+    /// client counts `character` in UTF-16 code units; the column is in
+    /// characters, like token columns.
+    fn position<'a>(
+        &'a self,
+        params: &'a Json,
+    ) -> Result<(&'a str, &'a str, usize, usize), (i32, String)> {
+        let (uri, source) = self.document(params)?;
+
+        let position = &params["position"];
+        match (position["line"].as_u64(), position["character"].as_u64()) {
+            // A document ending in a newline has an empty last line to point at.
+            (Some(line), Some(_)) if line >= source.split('\n').count() as u64 => Err((
+                INVALID_PARAMS,
+                format!("Line {} is outside the document.", line),
+            )),
+            (Some(line), Some(character)) => {
+                let line = line as usize + 1;
+                let column = column_of(source, line, character as usize);
+                Ok((uri, source, line, column))
+            }
+            _ => Err((INVALID_PARAMS, String::from("Missing position."))),
+        }
+    }
+
+    fn send(&mut self, message: Json) -> io::Result<()> {
+        protocol::write_message(&mut self.writer, &message)
+    }
+}
+
+fn initialize() -> Json {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": {},
+        },
+        "serverInfo": { "name": "rslox", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+/// An LSP range for `length` characters starting at a 1-based position in
+/// `source`.
+fn range(source: &str, line: usize, column: usize, length: usize) -> Json {
+    json!({
+        "start": { "line": line - 1, "character": utf16_offset(source, line, column) },
+        "end": { "line": line - 1, "character": utf16_offset(source, line, column + length) },
+    })
+}
+
+fn location(uri: &str, source: &str, token: &Token) -> Json {
+    json!({
+        "uri": uri,
+        "range": range(source, token.line, token.column, token.lexeme.chars().count()),
+    })
+}
+
+/// The UTF-16 offset of a 1-based character column. Columns past the end of
+/// the line count one unit per character.
+fn utf16_offset(source: &str, line: usize, column: usize) -> usize {
+    let text = source.lines().nth(line - 1).unwrap_or("");
+    let mut chars = text.chars();
+    (1..column)
+        .map(|_| chars.next().map_or(1, char::len_utf16))
+        .sum()
+}
+
+/// The 1-based character column at a UTF-16 offset. An offset inside a
+/// surrogate pair lands on the character it splits, and one past the end of
+/// the line stops just after its last character.
+fn column_of(source: &str, line: usize, character: usize) -> usize {
+    let text = source.lines().nth(line - 1).unwrap_or("");
+    let mut offset = 0;
+    let mut column = 1;
+
+    for c in text.chars() {
+        offset += c.len_utf16();
+        if offset > character {
+            return column;
+        }
+        column += 1;
+    }
+
+    column
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///emoji.lox";
+    const SOURCE: &str = "var s = \"😀\"; print s;\nprint \"😀\" +;\n";
+
+    /// Runs a scripted session and returns the exit status and every message
+    /// the server sent.
+    fn session(messages: &[Json]) -> (i32, Vec<Json>) {
+        let mut input = Vec::new();
+        for message in messages {
+            protocol::write_message(&mut input, message).unwrap();
+        }
+
+        let mut output = Vec::new();
+        let status = run(io::Cursor::new(input), &mut output);
+
+        let mut reader = io::Cursor::new(output);
+        let mut sent = Vec::new();
+        while let Some(message) = protocol::read_message(&mut reader).unwrap() {
+            sent.push(message);
+        }
+
+        (status, sent)
+    }
+
+    fn request(id: u64, method: &str, params: Json) -> Json {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    fn at(line: u64, character: u64) -> Json {
+        json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        })
+    }
+
+    fn full_session() -> (i32, Vec<Json>) {
+        session(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            notification(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": { "uri": URI, "languageId": "lox", "version": 1, "text": SOURCE },
+                }),
+            ),
+            // UTF-16 offset 20 is the `s` after the emoji, which takes two units.
+            request(2, "textDocument/definition", at(0, 20)),
+            request(3, "textDocument/hover", at(0, 20)),
+            request(4, "shutdown", Json::Null),
+            notification("exit", Json::Null),
+        ])
+    }
+
+    #[test]
+    fn initialize_advertises_capabilities() {
+        let (_, sent) = full_session();
+
+        assert_eq!(sent[0]["id"], 1);
+        let capabilities = &sent[0]["result"]["capabilities"];
+        assert_eq!(capabilities["textDocumentSync"], 1);
+        assert_eq!(capabilities["definitionProvider"], true);
+        assert_eq!(capabilities["hoverProvider"], true);
+    }
+
+    #[test]
+    fn diagnostics_use_utf16_offsets() {
+        let (_, sent) = full_session();
+
+        assert_eq!(sent[1]["method"], "textDocument/publishDiagnostics");
+        assert_eq!(sent[1]["params"]["uri"], URI);
+        assert_eq!(
+            sent[1]["params"]["diagnostics"],
+            json!([{
+                "range": {
+                    "start": { "line": 1, "character": 12 },
+                    "end": { "line": 1, "character": 13 },
+                },
+                "severity": 1,
+                "source": "rslox",
+                "message": "Expected expression.",
+            }])
+        );
+    }
+
+    #[test]
+    fn definition_resolves_past_non_ascii_text() {
+        let (_, sent) = full_session();
+
+        assert_eq!(sent[2]["id"], 2);
+        assert_eq!(
+            sent[2]["result"],
+            json!({
+                "uri": URI,
+                "range": {
+                    "start": { "line": 0, "character": 4 },
+                    "end": { "line": 0, "character": 5 },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn hover_shows_the_declaration() {
+        let (_, sent) = full_session();
+
+        assert_eq!(sent[3]["id"], 3);
+        assert_eq!(
+            sent[3]["result"],
+            json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": "```lox\nvar s = \"😀\"; print s;\n```",
+                },
+                "range": {
+                    "start": { "line": 0, "character": 20 },
+                    "end": { "line": 0, "character": 21 },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn shutdown_then_exit_succeeds() {
+        let (status, sent) = full_session();
+
+        assert_eq!(status, 0);
+        assert_eq!(sent.len(), 5);
+        assert_eq!(
+            sent[4],
+            json!({ "jsonrpc": "2.0", "id": 4, "result": null })
+        );
+    }
+
+    #[test]
+    fn exit_without_shutdown_fails() {
+        let (status, sent) = session(&[notification("exit", Json::Null)]);
+
+        assert_eq!(status, 1);
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn positions_convert_between_columns_and_utf16() {
+        let source = "a😀b\n";
+
+        assert_eq!(utf16_offset(source, 1, 1), 0);
+        assert_eq!(utf16_offset(source, 1, 3), 3);
+        assert_eq!(utf16_offset(source, 1, 5), 5);
+        assert_eq!(column_of(source, 1, 0), 1);
+        assert_eq!(column_of(source, 1, 2), 2);
+        assert_eq!(column_of(source, 1, 3), 3);
+        assert_eq!(column_of(source, 1, 6), 4);
+        assert_eq!(column_of(source, 1, usize::MAX), 4);
+    }
+
+    #[test]
+    fn positions_outside_the_document_are_rejected() {
+        let open = notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "lox", "version": 1, "text": SOURCE },
+            }),
+        );
+        let (_, sent) = session(&[
+            open,
+            request(1, "textDocument/hover", at(2, 0)),
+            request(2, "textDocument/hover", at(3, 0)),
+            request(3, "textDocument/hover", at(u64::MAX, u64::MAX)),
+        ]);
+
+        let replies: Vec<_> = sent
+            .iter()
+            .filter(|message| message["id"].is_u64())
+            .collect();
+        assert!(replies[0]["error"].is_null());
+        assert_eq!(replies[1]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(
+            replies[1]["error"]["message"],
+            "Line 3 is outside the document."
+        );
+        assert_eq!(replies[2]["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    ast::{
+        parser::Parser,
+        statement::{BlockStmt, Stmt},
+    },
+    error::{ErrorKind, RuntimeError},
+    interpreter::{is_equal, is_truthy, Interpreter},
+    lexer::{scanner::Scanner, token::Token},
+    value::Value,
+};
+
+use super::{string_arg, NativeFunction};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction::new("assertEq", Some(2), assert_eq),
+    NativeFunction::new("assertTrue", Some(1), assert_true),
+    NativeFunction::new("assertError", Some(2), assert_error),
+];
+
+/// `assertEq(actual, expected)`. Lists and maps are compared element by element.
+fn assert_eq(_: &mut Interpreter, paren: &Token, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !values_equal(&args[0], &args[1]) {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            format!(
+                "Expected {} but got {}.",
+                describe(&args[1]),
+                describe(&args[0])
+            ),
+        ));
+    }
+
+    Ok(Value::Nil)
+}
+
+fn assert_true(_: &mut Interpreter, paren: &Token, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !is_truthy(&args[0]) {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            format!("Expected a truthy value but got {}.", describe(&args[0])),
+        ));
+    }
+
+    Ok(Value::Nil)
+}
+
+/// `assertError(code, message)` runs `code` in a new scope and checks that it
+/// raises a runtime error whose message contains `message`.
+fn assert_error(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let code = string_arg(paren, "assertError", args, 0)?;
+    let expected = string_arg(paren, "assertError", args, 1)?;
+
+    let (tokens, scan_errors) = Scanner::new(String::from(code)).scan_tokens_with_errors();
+    let (statements, parse_errors) = Parser::new(tokens).parse_with_errors();
+
+    let syntax_error = scan_errors
+        .into_iter()
+        .map(|err| err.message)
+        .chain(parse_errors.into_iter().map(|err| err.message))
+        .next();
+    if let Some(message) = syntax_error {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            format!("Syntax error in code passed to 'assertError': {}", message),
+        ));
+    }
+
+    let block = Stmt::Block(BlockStmt::new(paren.clone(), statements));
+    let message = match interpreter.execute(&block) {
+        Ok(()) => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Expected an error but none was raised."),
+            ))
+        }
+        Err(err) if err.kind != ErrorKind::Error => return Err(err),
+        Err(err) => err.message,
+    };
+
+    if !message.contains(expected) {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            format!(
+                "Expected an error containing \"{}\" but got \"{}\".",
+                expected, message
+            ),
+        ));
+    }
+
+    Ok(Value::Nil)
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::List(l), Value::List(r)) => {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)| values_equal(l, r))
+        }
+        (Value::Map(l), Value::Map(r)) => {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len()
+                && l.iter()
+                    .zip(r.iter())
+                    .all(|((lk, lv), (rk, rv))| lk == rk && values_equal(lv, rv))
+        }
+        _ => is_equal(left, right),
+    }
+}
+
+/// Shows strings quoted so that `"1"` and `1` can be told apart.
+fn describe(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        value => value.to_string(),
+    }
+}
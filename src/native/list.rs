@@ -0,0 +1,54 @@
+use crate::{error::RuntimeError, interpreter::Interpreter, lexer::token::Token, value::Value};
+
+use super::{list_arg, map_arg, number_arg, string_arg, NativeFunction};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction::new("len", Some(1), len),
+    NativeFunction::new("get", Some(2), get),
+    NativeFunction::new("keys", Some(1), keys),
+];
+
+fn len(_: &mut Interpreter, paren: &Token, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+        Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+        Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Argument 1 to 'len' must be a string, a list or a map."),
+        )),
+    }
+}
+
+/// `get(list, index)`, or `get(map, key)`, which is nil for a missing key.
+fn get(_: &mut Interpreter, paren: &Token, args: &[Value]) -> Result<Value, RuntimeError> {
+    if let Value::Map(map) = &args[0] {
+        let key = string_arg(paren, "get", args, 1)?;
+        return Ok(map.borrow().get(key).cloned().unwrap_or(Value::Nil));
+    }
+
+    let list = list_arg(paren, "get", args, 0)?;
+    let index = number_arg(paren, "get", args, 1)?;
+
+    let list = list.borrow();
+    if index < 0.0 || index.fract() != 0.0 || index as usize >= list.len() {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("List index out of range."),
+        ));
+    }
+
+    Ok(list[index as usize].clone())
+}
+
+/// `keys(map)` returns the keys of `map` in sorted order.
+fn keys(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let map = map_arg(paren, "keys", args, 0)?;
+    let keys = map.borrow().keys().cloned().map(Value::String).collect();
+
+    interpreter.allocate_value(paren, Value::list(keys))
+}
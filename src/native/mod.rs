@@ -0,0 +1,122 @@
+mod assert;
+mod list;
+pub mod random;
+mod regex;
+mod system;
+pub mod time;
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use crate::{
+    environment::Environment, error::RuntimeError, interpreter::Interpreter, lexer::token::Token,
+    value::Value,
+};
+
+pub type NativeFn = fn(&mut Interpreter, &Token, &[Value]) -> Result<Value, RuntimeError>;
+
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: Option<usize>,
+    pub function: NativeFn,
+}
+
+impl NativeFunction {
+    pub const fn new(name: &'static str, arity: Option<usize>, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+pub fn natives() -> impl Iterator<Item = &'static NativeFunction> {
+    assert::NATIVES
+        .iter()
+        .chain(list::NATIVES)
+        .chain(random::NATIVES)
+        .chain(regex::NATIVES)
+        .chain(system::NATIVES)
+        .chain(time::NATIVES)
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    natives().map(|native| native.name)
+}
+
+pub fn define_globals(environment: &mut Environment) {
+    for native in natives() {
+        environment.define(
+            String::from(native.name),
+            Value::NativeFunction(native.clone()),
+        );
+    }
+}
+
+pub fn string_arg<'a>(
+    paren: &Token,
+    name: &str,
+    args: &'a [Value],
+    index: usize,
+) -> Result<&'a str, RuntimeError> {
+    match &args[index] {
+        Value::String(s) => Ok(s),
+        _ => Err(argument_error(paren, name, index, "a string")),
+    }
+}
+
+pub fn number_arg(
+    paren: &Token,
+    name: &str,
+    args: &[Value],
+    index: usize,
+) -> Result<f64, RuntimeError> {
+    match &args[index] {
+        Value::Number(n) => Ok(*n),
+        _ => Err(argument_error(paren, name, index, "a number")),
+    }
+}
+
+pub fn map_arg(
+    paren: &Token,
+    name: &str,
+    args: &[Value],
+    index: usize,
+) -> Result<Rc<RefCell<BTreeMap<String, Value>>>, RuntimeError> {
+    match &args[index] {
+        Value::Map(map) => Ok(map.clone()),
+        _ => Err(argument_error(paren, name, index, "a map")),
+    }
+}
+
+pub fn list_arg(
+    paren: &Token,
+    name: &str,
+    args: &[Value],
+    index: usize,
+) -> Result<Rc<RefCell<Vec<Value>>>, RuntimeError> {
+    match &args[index] {
+        Value::List(list) => Ok(list.clone()),
+        _ => Err(argument_error(paren, name, index, "a list")),
+    }
+}
+
+fn argument_error(paren: &Token, name: &str, index: usize, expected: &str) -> RuntimeError {
+    RuntimeError::new(
+        paren.clone(),
+        format!("Argument {} to '{}' must be {}.", index + 1, name, expected),
+    )
+}
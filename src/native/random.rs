@@ -0,0 +1,295 @@
+use crate::{error::RuntimeError, interpreter::Interpreter, lexer::token::Token, value::Value};
+
+use super::{list_arg, number_arg, NativeFunction};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction::new("seed", Some(1), seed),
+    NativeFunction::new("random", Some(0), random),
+    NativeFunction::new("randomInt", Some(2), random_int),
+    NativeFunction::new("shuffle", Some(1), shuffle),
+    NativeFunction::new("choice", Some(1), choice),
+];
+
+/// xoshiro256** seeded through SplitMix64, as described at
+/// <https://prng.di.unimi.it/>. Only integer arithmetic is used, so a given seed
+/// produces the same sequence on every platform.
+#[derive(Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut splitmix = seed;
+        let mut state = [0; 4];
+        for word in &mut state {
+            splitmix = splitmix.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = splitmix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            *word = z ^ (z >> 31);
+        }
+
+        Self { state }
+    }
+
+    /// Seeds from the system time, for runs that never call `seed`.
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+
+        result
+    }
+
+    /// A float in `[0, 1)` built from the top 53 bits of the next output.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// An integer in `[0, bound)`, using rejection sampling to avoid modulo bias.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+fn seed(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let seed = integer_arg(paren, "seed", args, 0)?;
+    interpreter.rng = Rng::new(seed as u64);
+
+    Ok(Value::Nil)
+}
+
+fn random(interpreter: &mut Interpreter, _: &Token, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(interpreter.rng.next_f64()))
+}
+
+/// `randomInt(lo, hi)` returns an integer between `lo` and `hi`, both inclusive.
+fn random_int(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let lo = integer_arg(paren, "randomInt", args, 0)?;
+    let hi = integer_arg(paren, "randomInt", args, 1)?;
+
+    if lo > hi {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("'randomInt' lower bound must not exceed upper bound."),
+        ));
+    }
+
+    let span = hi.wrapping_sub(lo) as u64;
+    let offset = match span.checked_add(1) {
+        Some(bound) => interpreter.rng.below(bound),
+        None => interpreter.rng.next_u64(),
+    };
+
+    Ok(Value::Number(lo.wrapping_add(offset as i64) as f64))
+}
+
+/// Shuffles the list in place with Fisher-Yates and returns it.
+fn shuffle(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let list = list_arg(paren, "shuffle", args, 0)?;
+
+    {
+        let mut values = list.borrow_mut();
+        for i in (1..values.len()).rev() {
+            interpreter.check_interrupt(paren)?;
+            let j = interpreter.rng.below(i as u64 + 1) as usize;
+            values.swap(i, j);
+        }
+    }
+
+    Ok(Value::List(list))
+}
+
+fn choice(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let list = list_arg(paren, "choice", args, 0)?;
+    let values = list.borrow();
+
+    if values.is_empty() {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Cannot choose from an empty list."),
+        ));
+    }
+
+    let index = interpreter.rng.below(values.len() as u64) as usize;
+    Ok(values[index].clone())
+}
+
+fn integer_arg(
+    paren: &Token,
+    name: &str,
+    args: &[Value],
+    index: usize,
+) -> Result<i64, RuntimeError> {
+    let n = number_arg(paren, name, args, index)?;
+
+    if n.fract() != 0.0 || n.abs() > 9007199254740992.0 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            format!("Argument {} to '{}' must be an integer.", index + 1, name),
+        ));
+    }
+
+    Ok(n as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::token_type::TokenType;
+
+    fn seeded(seed_value: f64) -> (Interpreter, Token) {
+        let paren = Token::new(TokenType::RightParen, String::from(")"), None, 1, 0);
+        let mut interpreter = Interpreter::new();
+        seed(&mut interpreter, &paren, &[Value::Number(seed_value)]).unwrap();
+        (interpreter, paren)
+    }
+
+    #[test]
+    fn matches_the_reference_xoshiro256_starstar() {
+        let mut rng = Rng::new(42);
+        let outputs: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            outputs,
+            [
+                1546998764402558742,
+                6990951692964543102,
+                12544586762248559009
+            ]
+        );
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let (mut a, paren) = seeded(7.0);
+        let (mut b, _) = seeded(7.0);
+        for _ in 0..10 {
+            assert_eq!(
+                random(&mut a, &paren, &[]).unwrap(),
+                random(&mut b, &paren, &[]).unwrap()
+            );
+        }
+
+        let (mut c, _) = seeded(8.0);
+        assert_ne!(
+            random(&mut a, &paren, &[]).unwrap(),
+            random(&mut c, &paren, &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn random_is_in_the_unit_interval() {
+        let mut rng = Rng::new(1);
+        assert!((0..1000)
+            .map(|_| rng.next_f64())
+            .all(|x| (0.0..1.0).contains(&x)));
+    }
+
+    #[test]
+    fn random_int_covers_both_bounds() {
+        let (mut interpreter, paren) = seeded(3.0);
+        let args = [Value::Number(-2.0), Value::Number(2.0)];
+        let mut seen = std::collections::BTreeSet::new();
+        for _ in 0..200 {
+            match random_int(&mut interpreter, &paren, &args).unwrap() {
+                Value::Number(n) => seen.insert(n as i64),
+                value => panic!("unexpected {}", value),
+            };
+        }
+        assert_eq!(seen.into_iter().collect::<Vec<_>>(), [-2, -1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn random_int_rejects_bad_bounds() {
+        let (mut interpreter, paren) = seeded(3.0);
+        let err = random_int(
+            &mut interpreter,
+            &paren,
+            &[Value::Number(2.0), Value::Number(1.0)],
+        );
+        assert_eq!(
+            err.unwrap_err().message,
+            "'randomInt' lower bound must not exceed upper bound."
+        );
+
+        let err = random_int(
+            &mut interpreter,
+            &paren,
+            &[Value::Number(0.5), Value::Number(1.0)],
+        );
+        assert_eq!(
+            err.unwrap_err().message,
+            "Argument 1 to 'randomInt' must be an integer."
+        );
+    }
+
+    #[test]
+    fn shuffle_is_a_deterministic_permutation() {
+        let numbers = || Value::list((0..10).map(|n| Value::Number(n as f64)).collect());
+        let (mut a, paren) = seeded(5.0);
+        let (mut b, _) = seeded(5.0);
+
+        let first = shuffle(&mut a, &paren, &[numbers()]).unwrap();
+        let second = shuffle(&mut b, &paren, &[numbers()]).unwrap();
+        assert_eq!(first.to_string(), second.to_string());
+
+        let Value::List(list) = first else {
+            panic!("shuffle returns its list");
+        };
+        let mut sorted: Vec<f64> = list
+            .borrow()
+            .iter()
+            .map(|value| match value {
+                Value::Number(n) => *n,
+                _ => f64::NAN,
+            })
+            .collect();
+        sorted.sort_by(f64::total_cmp);
+        assert_eq!(sorted, (0..10).map(|n| n as f64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn choice_from_an_empty_list_fails() {
+        let (mut interpreter, paren) = seeded(1.0);
+        let err = choice(&mut interpreter, &paren, &[Value::list(Vec::new())]).unwrap_err();
+        assert_eq!(err.message, "Cannot choose from an empty list.");
+    }
+}
@@ -0,0 +1,209 @@
+use regex::Regex;
+
+use crate::{error::RuntimeError, interpreter::Interpreter, lexer::token::Token, value::Value};
+
+use super::{string_arg, NativeFunction};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction::new("match", Some(2), match_),
+    NativeFunction::new("findAll", Some(2), find_all),
+    NativeFunction::new("replaceAll", Some(3), replace_all),
+    NativeFunction::new("split", Some(2), split),
+];
+
+/// Returns the compiled regex for `pattern`, compiling and caching it on first use.
+fn compile(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    pattern: &str,
+) -> Result<Regex, RuntimeError> {
+    if let Some(regex) = interpreter.regexes.get(pattern) {
+        return Ok(regex.clone());
+    }
+
+    match Regex::new(pattern) {
+        Ok(regex) => {
+            interpreter
+                .regexes
+                .insert(String::from(pattern), regex.clone());
+            Ok(regex)
+        }
+        Err(err) => Err(RuntimeError::new(
+            paren.clone(),
+            format!("Invalid regular expression: {}", err),
+        )),
+    }
+}
+
+/// `match(re, s)` returns a list holding the whole match followed by each capture
+/// group (`nil` for groups that did not participate), or `nil` if nothing matched.
+fn match_(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let regex = compile(interpreter, paren, string_arg(paren, "match", args, 0)?)?;
+    let text = string_arg(paren, "match", args, 1)?;
+
+    let captures = match regex.captures(text) {
+        Some(captures) => captures,
+        None => return Ok(Value::Nil),
+    };
+
+    let groups = captures
+        .iter()
+        .map(|group| match group {
+            Some(group) => Value::String(String::from(group.as_str())),
+            None => Value::Nil,
+        })
+        .collect();
+
+    interpreter.allocate_value(paren, Value::list(groups))
+}
+
+fn find_all(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let regex = compile(interpreter, paren, string_arg(paren, "findAll", args, 0)?)?;
+    let text = string_arg(paren, "findAll", args, 1)?;
+
+    let matches = regex
+        .find_iter(text)
+        .map(|m| {
+            interpreter.check_interrupt(paren)?;
+            Ok(Value::String(String::from(m.as_str())))
+        })
+        .collect::<Result<_, RuntimeError>>()?;
+
+    interpreter.allocate_value(paren, Value::list(matches))
+}
+
+/// `replaceAll(re, s, replacement)` where the replacement may refer to capture
+/// groups as `$1` or `${name}`.
+fn replace_all(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let regex = compile(
+        interpreter,
+        paren,
+        string_arg(paren, "replaceAll", args, 0)?,
+    )?;
+    let text = string_arg(paren, "replaceAll", args, 1)?;
+    let replacement = string_arg(paren, "replaceAll", args, 2)?;
+
+    let replaced = regex.replace_all(text, replacement).into_owned();
+
+    interpreter.allocate_value(paren, Value::String(replaced))
+}
+
+fn split(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let regex = compile(interpreter, paren, string_arg(paren, "split", args, 0)?)?;
+    let text = string_arg(paren, "split", args, 1)?;
+
+    let parts = regex
+        .split(text)
+        .map(|part| {
+            interpreter.check_interrupt(paren)?;
+            Ok(Value::String(String::from(part)))
+        })
+        .collect::<Result<_, RuntimeError>>()?;
+
+    interpreter.allocate_value(paren, Value::list(parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::token_type::TokenType, native::NativeFn};
+
+    fn call(function: NativeFn, args: &[&str]) -> Result<Value, RuntimeError> {
+        let paren = Token::new(TokenType::RightParen, String::from(")"), None, 1, 0);
+        let args: Vec<Value> = args
+            .iter()
+            .map(|arg| Value::String(arg.to_string()))
+            .collect();
+        function(&mut Interpreter::new(), &paren, &args)
+    }
+
+    fn strings(values: &[Option<&str>]) -> Value {
+        Value::list(
+            values
+                .iter()
+                .map(|value| match value {
+                    Some(s) => Value::String(s.to_string()),
+                    None => Value::Nil,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn match_returns_whole_match_and_groups() {
+        let value = call(match_, &[r"(\d+)-(\d+)(x)?", "call 555-1234 now"]).unwrap();
+        assert_eq!(
+            value,
+            strings(&[Some("555-1234"), Some("555"), Some("1234"), None])
+        );
+    }
+
+    #[test]
+    fn match_without_a_match_is_nil() {
+        assert_eq!(call(match_, &["z+", "abc"]).unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn find_all_returns_every_match() {
+        let value = call(find_all, &[r"\w+", "one, two three"]).unwrap();
+        assert_eq!(value, strings(&[Some("one"), Some("two"), Some("three")]));
+    }
+
+    #[test]
+    fn replace_all_expands_backreferences() {
+        let value = call(replace_all, &[r"(?P<k>\w+)=(\w+)", "a=1 b=2", "$2=${k}"]).unwrap();
+        assert_eq!(value, Value::String(String::from("1=a 2=b")));
+    }
+
+    #[test]
+    fn split_by_pattern() {
+        let value = call(split, &[r"\s*,\s*", "a , b,c"]).unwrap();
+        assert_eq!(value, strings(&[Some("a"), Some("b"), Some("c")]));
+    }
+
+    #[test]
+    fn invalid_pattern_reports_the_compiler_message() {
+        let err = call(match_, &["(unclosed", "text"]).unwrap_err();
+        assert!(err.message.starts_with("Invalid regular expression: "));
+        assert!(err.message.contains("unclosed group"), "{}", err.message);
+    }
+
+    #[test]
+    fn compiled_patterns_are_cached() {
+        let paren = Token::new(TokenType::RightParen, String::from(")"), None, 1, 0);
+        let mut interpreter = Interpreter::new();
+        compile(&mut interpreter, &paren, "a+").unwrap();
+        compile(&mut interpreter, &paren, "a+").unwrap();
+        assert_eq!(interpreter.regexes.len(), 1);
+    }
+
+    #[test]
+    fn find_all_stops_when_interrupted() {
+        let paren = Token::new(TokenType::RightParen, String::from(")"), None, 1, 0);
+        let mut interpreter = Interpreter::new();
+        interpreter.interrupt_handle().interrupt();
+
+        let args = [
+            Value::String(String::from("a")),
+            Value::String(String::from("aaa")),
+        ];
+        let err = find_all(&mut interpreter, &paren, &args).unwrap_err();
+        assert_eq!(err.kind, crate::error::ErrorKind::Interrupted);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{error::RuntimeError, interpreter::Interpreter, lexer::token::Token, value::Value};
+
+use super::{number_arg, string_arg, NativeFunction};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction::new("args", Some(0), args),
+    NativeFunction::new("scriptPath", Some(0), script_path),
+    NativeFunction::new("getenv", Some(1), getenv),
+    NativeFunction::new("exit", Some(1), exit),
+];
+
+/// `args()` returns the command-line arguments that followed the script path.
+fn args(interpreter: &mut Interpreter, paren: &Token, _: &[Value]) -> Result<Value, RuntimeError> {
+    let args = interpreter
+        .script_args
+        .iter()
+        .map(|arg| Value::String(arg.clone()))
+        .collect();
+
+    interpreter.allocate_value(paren, Value::list(args))
+}
+
+fn script_path(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    _: &[Value],
+) -> Result<Value, RuntimeError> {
+    match interpreter.script_path.clone() {
+        Some(path) => interpreter.allocate_value(paren, Value::String(path)),
+        None => Ok(Value::Nil),
+    }
+}
+
+fn getenv(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let name = string_arg(paren, "getenv", args, 0)?;
+
+    match std::env::var(name) {
+        Ok(value) => interpreter.allocate_value(paren, Value::String(value)),
+        Err(_) => Ok(Value::Nil),
+    }
+}
+
+/// `exit(code)` unwinds the whole script; the embedder decides what to do with
+/// the status.
+fn exit(_: &mut Interpreter, paren: &Token, args: &[Value]) -> Result<Value, RuntimeError> {
+    let code = number_arg(paren, "exit", args, 0)?;
+
+    if code.fract() != 0.0 || code < i32::MIN as f64 || code > i32::MAX as f64 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Argument 1 to 'exit' must be an integer."),
+        ));
+    }
+
+    Err(RuntimeError::exit(paren.clone(), code as i32))
+}
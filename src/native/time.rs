@@ -0,0 +1,242 @@
+use chrono::{
+    format::StrftimeItems, DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset,
+    TimeZone, Utc,
+};
+
+use crate::{error::RuntimeError, interpreter::Interpreter, lexer::token::Token, value::Value};
+
+use super::{number_arg, string_arg, NativeFunction};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction::new("clock", Some(0), now),
+    NativeFunction::new("now", Some(0), now),
+    NativeFunction::new("formatTime", Some(2), format_time),
+    NativeFunction::new("formatLocalTime", Some(2), format_local_time),
+    NativeFunction::new("parseTime", Some(2), parse_time),
+    NativeFunction::new("localOffset", Some(1), local_offset),
+];
+
+/// Source of wall-clock time for the time natives. Timestamps and durations are
+/// plain `Number`s of seconds, so duration arithmetic is ordinary arithmetic.
+pub trait Clock {
+    /// Seconds since the Unix epoch.
+    fn now(&self) -> f64;
+
+    /// Offset of local time from UTC at `timestamp`, in seconds east of UTC.
+    fn local_offset(&self, timestamp: f64) -> i32;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        let now = Utc::now();
+        now.timestamp() as f64 + now.timestamp_subsec_nanos() as f64 / 1e9
+    }
+
+    fn local_offset(&self, timestamp: f64) -> i32 {
+        match Local.timestamp_opt(timestamp.floor() as i64, 0).single() {
+            Some(time) => time.offset().fix().local_minus_utc(),
+            None => 0,
+        }
+    }
+}
+
+/// A clock frozen at `timestamp` in a zone `offset` seconds east of UTC, so that
+/// hosts can run scripts deterministically.
+pub struct FixedClock {
+    pub timestamp: f64,
+    pub offset: i32,
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> f64 {
+        self.timestamp
+    }
+
+    fn local_offset(&self, _: f64) -> i32 {
+        self.offset
+    }
+}
+
+fn now(interpreter: &mut Interpreter, _: &Token, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(interpreter.clock.now()))
+}
+
+fn format_time(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let timestamp = number_arg(paren, "formatTime", args, 0)?;
+    let format = string_arg(paren, "formatTime", args, 1)?;
+
+    let formatted = format_with_offset(paren, timestamp, format, 0)?;
+
+    interpreter.allocate_value(paren, formatted)
+}
+
+fn format_local_time(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let timestamp = number_arg(paren, "formatLocalTime", args, 0)?;
+    let format = string_arg(paren, "formatLocalTime", args, 1)?;
+    let offset = interpreter.clock.local_offset(timestamp);
+
+    let formatted = format_with_offset(paren, timestamp, format, offset)?;
+
+    interpreter.allocate_value(paren, formatted)
+}
+
+/// `parseTime(s, fmt)` returns the timestamp described by `s`. Inputs without an
+/// offset (`%z`) are taken to be UTC, and inputs without a time to be midnight.
+fn parse_time(_: &mut Interpreter, paren: &Token, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = string_arg(paren, "parseTime", args, 0)?;
+    let format = string_arg(paren, "parseTime", args, 1)?;
+
+    let time = DateTime::parse_from_str(text, format)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, format).map(|time| time.and_utc()))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, format)
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        });
+
+    match time {
+        Ok(time) => Ok(Value::Number(
+            time.timestamp() as f64 + time.timestamp_subsec_nanos() as f64 / 1e9,
+        )),
+        Err(err) => Err(RuntimeError::new(
+            paren.clone(),
+            format!("Cannot parse '{}' as '{}': {}.", text, format, err),
+        )),
+    }
+}
+
+fn local_offset(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let timestamp = number_arg(paren, "localOffset", args, 0)?;
+
+    Ok(Value::Number(
+        interpreter.clock.local_offset(timestamp) as f64
+    ))
+}
+
+fn format_with_offset(
+    paren: &Token,
+    timestamp: f64,
+    format: &str,
+    offset: i32,
+) -> Result<Value, RuntimeError> {
+    let items = match StrftimeItems::new(format).parse() {
+        Ok(items) => items,
+        Err(_) => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                format!("Invalid time format '{}'.", format),
+            ))
+        }
+    };
+
+    let seconds = timestamp.floor();
+    let nanos = ((timestamp - seconds) * 1e9) as u32;
+
+    let time = DateTime::from_timestamp(seconds as i64, nanos);
+    let zone = FixedOffset::east_opt(offset);
+
+    match (time, zone) {
+        (Some(time), Some(zone)) => Ok(Value::String(
+            time.with_timezone(&zone)
+                .format_with_items(items.iter())
+                .to_string(),
+        )),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            format!("Timestamp {} is out of range.", timestamp),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{lexer::token_type::TokenType, native::NativeFn};
+
+    fn call(function: NativeFn, args: &[Value]) -> Result<Value, RuntimeError> {
+        let paren = Token::new(TokenType::RightParen, String::from(")"), None, 1, 0);
+        let mut interpreter = Interpreter::new();
+        interpreter.set_clock(Rc::new(FixedClock {
+            timestamp: 86_400.5,
+            offset: 3_600,
+        }));
+        function(&mut interpreter, &paren, args)
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(String::from(s))
+    }
+
+    #[test]
+    fn now_reads_the_injected_clock() {
+        assert_eq!(call(now, &[]).unwrap(), Value::Number(86_400.5));
+    }
+
+    #[test]
+    fn format_time_is_utc() {
+        let value = call(
+            format_time,
+            &[Value::Number(86_400.5), string("%Y-%m-%d %H:%M:%S%.3f")],
+        );
+        assert_eq!(value.unwrap(), string("1970-01-02 00:00:00.500"));
+    }
+
+    #[test]
+    fn format_local_time_applies_the_clock_offset() {
+        let value = call(format_local_time, &[Value::Number(0.0), string("%H:%M %z")]);
+        assert_eq!(value.unwrap(), string("01:00 +0100"));
+    }
+
+    #[test]
+    fn parse_time_accepts_offsets_and_bare_dates() {
+        let value = call(
+            parse_time,
+            &[
+                string("1970-01-01 01:00 +0100"),
+                string("%Y-%m-%d %H:%M %z"),
+            ],
+        );
+        assert_eq!(value.unwrap(), Value::Number(0.0));
+
+        let value = call(parse_time, &[string("1970-01-02"), string("%Y-%m-%d")]);
+        assert_eq!(value.unwrap(), Value::Number(86_400.0));
+    }
+
+    #[test]
+    fn local_offset_reads_the_injected_clock() {
+        assert_eq!(
+            call(local_offset, &[Value::Number(0.0)]).unwrap(),
+            Value::Number(3_600.0)
+        );
+    }
+
+    #[test]
+    fn bad_formats_and_inputs_are_runtime_errors() {
+        let err = call(format_time, &[Value::Number(0.0), string("%Q")]).unwrap_err();
+        assert_eq!(err.message, "Invalid time format '%Q'.");
+
+        let err = call(parse_time, &[string("yesterday"), string("%Y-%m-%d")]).unwrap_err();
+        assert!(
+            err.message
+                .starts_with("Cannot parse 'yesterday' as '%Y-%m-%d'"),
+            "{}",
+            err.message
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use std::{io::Write, path::PathBuf};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::{
+    ast::expr::Expr,
+    debugger,
+    environment::Environment,
+    error::{self, ErrorKind, LoxError, RuntimeError},
+    interpreter::Interpreter,
+    lexer::{scanner::Scanner, token_type::TokenType},
+    lox::Lox,
+    native,
+    value::Value,
+};
+
+const HISTORY_FILE: &str = ".rslox_history";
+
+const HELP: &str = "\
+:env          print the global variables
+:reset        clear the global variables
+:load FILE    run a file in this session
+:tokens CODE  print the tokens for a snippet
+:quit         leave the REPL";
+
+/// Runs the interactive prompt until end of input or `:quit`. Every line runs
+/// in the same session, and the value of an expression statement is echoed.
+/// Input that leaves a brace, parenthesis, string or block comment open is
+/// continued on the next line.
+pub fn run() {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("Failed to start line editor: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session.
+        let _ = editor.load_history(path);
+    }
+
+    let mut lox = Lox::with_interpreter(crate::new_interpreter());
+
+    while let Some(source) = read_input(&mut editor) {
+        if source.trim().is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(source.trim_end());
+
+        let status = match source.trim().strip_prefix(':') {
+            Some(command) => meta_command(command, &mut lox),
+            None => eval(&source, &mut lox),
+        };
+
+        if let Some(code) = status {
+            save_history(&mut editor, &history);
+            std::process::exit(code);
+        }
+
+        error::reset_error();
+    }
+
+    save_history(&mut editor, &history);
+}
+
+/// Runs a `:` command. Returns the status to exit with, if any.
+fn meta_command(command: &str, lox: &mut Lox) -> Option<i32> {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+    let interpreter = lox.interpreter();
+
+    match name {
+        "env" => {
+            let globals = debugger::scopes(&interpreter.environment).pop();
+            for (name, value) in globals.unwrap_or_default() {
+                let _ = writeln!(interpreter.output, "{} = {}", name, value);
+            }
+        }
+        "reset" => reset_globals(interpreter),
+        "load" => match lox.run_file(argument) {
+            Ok(_) => {}
+            Err(err) => return report(err),
+        },
+        "tokens" => {
+            crate::dump_tokens(String::from(argument), &mut interpreter.output);
+        }
+        "quit" => return Some(0),
+        "help" => {
+            let _ = writeln!(interpreter.output, "{}", HELP);
+        }
+        _ => {
+            let _ = writeln!(
+                interpreter.output,
+                "Unknown command ':{}'. Type ':help' for a list.",
+                name
+            );
+        }
+    }
+
+    None
+}
+
+/// Forgets the variables the session defined. Natives come back as they were
+/// at startup and host functions are kept; the clock, random generator, output
+/// and limits are left alone.
+fn reset_globals(interpreter: &mut Interpreter) {
+    let mut globals = Environment::new();
+    native::define_globals(&mut globals);
+
+    for (name, value) in interpreter.environment.values() {
+        if let Value::HostFunction(_) = value {
+            globals.define(name.clone(), value.clone());
+        }
+    }
+
+    interpreter.environment = globals;
+}
+
+/// Runs `source` in the session, echoing the value of each expression
+/// statement. A bare expression needs no semicolon. Returns the status the
+/// script passed to `exit`, if any.
+fn eval(source: &str, lox: &mut Lox) -> Option<i32> {
+    match lox.eval_each(source, echo) {
+        Ok(_) => None,
+        Err(err) => report(err),
+    }
+}
+
+/// Reports an error the way a script run would. Returns the status the script
+/// passed to `exit`, if that is what stopped it.
+fn report(err: LoxError) -> Option<i32> {
+    match err {
+        LoxError::Io(_) => eprintln!("{}", err),
+        LoxError::Scan(errors) => {
+            error::report_syntax_errors(&errors, &[]);
+        }
+        LoxError::Parse(errors) => {
+            error::report_syntax_errors(&[], &errors);
+        }
+        LoxError::Runtime(err) => match err.kind {
+            ErrorKind::Exit(code) => return Some(code),
+            _ => error::runtime_error(&err),
+        },
+    }
+
+    None
+}
+
+/// Prints the value of an expression statement, as Python does, unless it is
+/// nil or the result of an assignment.
+fn echo(interpreter: &mut Interpreter, expr: &Expr, value: &Value) -> Result<(), RuntimeError> {
+    if matches!(expr, Expr::Assign(_)) || matches!(value, Value::Nil) {
+        return Ok(());
+    }
+
+    writeln!(interpreter.output, "{}", value).map_err(|err| {
+        RuntimeError::new(
+            expr.first_token().clone(),
+            format!("Failed to write output: {}", err),
+        )
+    })
+}
+
+/// Reads one complete piece of input, prompting with `..` for continuation
+/// lines. Ctrl-C discards what has been typed so far. Returns `None` at end of
+/// input.
+fn read_input(editor: &mut DefaultEditor) -> Option<String> {
+    let mut source = String::new();
+
+    loop {
+        let prompt = if source.is_empty() { "> " } else { ".. " };
+
+        match editor.readline(prompt) {
+            Ok(line) => {
+                source.push_str(&line);
+                source.push('\n');
+
+                if !is_incomplete(&source) {
+                    return Some(source);
+                }
+            }
+            Err(ReadlineError::Interrupted) => source.clear(),
+            Err(ReadlineError::Eof) => return None,
+            Err(err) => {
+                eprintln!("Failed to read line: {}", err);
+                return None;
+            }
+        }
+    }
+}
+
+/// Whether `source` stops inside a string, a block comment, or before closing
+/// every brace and parenthesis it opened.
+fn is_incomplete(source: &str) -> bool {
+    let (tokens, errors) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+
+    let unterminated = errors.iter().any(|err| {
+        err.message == "Unterminated string." || err.message == "Unterminated block comment."
+    });
+
+    let depth = tokens
+        .iter()
+        .fold(0, |depth, token| match token.token_type {
+            TokenType::LeftBrace | TokenType::LeftParen => depth + 1,
+            TokenType::RightBrace | TokenType::RightParen => depth - 1,
+            _ => depth,
+        });
+
+    unterminated || depth > 0
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+fn save_history(editor: &mut DefaultEditor, history: &Option<PathBuf>) {
+    if let Some(path) = history {
+        if let Err(err) = editor.save_history(path) {
+            eprintln!("Failed to save history: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{capture::Capture, limits::Limits, native::random::Rng};
+
+    fn session() -> (Lox, Capture) {
+        let output = Capture::new();
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+        (lox, output)
+    }
+
+    #[test]
+    fn expressions_are_echoed() {
+        let (mut lox, output) = session();
+
+        assert_eq!(eval("var a = 2;", &mut lox), None);
+        eval("a * 3", &mut lox);
+        eval("a = 5", &mut lox);
+        eval("nil", &mut lox);
+        eval("a; print \"then\"; a + 1;", &mut lox);
+        assert_eq!(output.contents(), "6\n5\nthen\n6\n");
+    }
+
+    #[test]
+    fn exit_ends_the_session_with_its_status() {
+        let (mut lox, output) = session();
+
+        assert_eq!(eval("print 1; exit(3); print 2;", &mut lox), Some(3));
+        assert_eq!(output.contents(), "1\n");
+    }
+
+    #[test]
+    fn env_lists_the_session_globals() {
+        let (mut lox, output) = session();
+
+        eval("var b = \"two\"; var a = 1;", &mut lox);
+        assert_eq!(meta_command("env", &mut lox), None);
+        assert_eq!(output.contents(), "a = 1\nb = two\n");
+    }
+
+    #[test]
+    fn reset_clears_only_the_globals() {
+        let (mut lox, output) = session();
+        let limits = Limits {
+            steps: Some(1_000),
+            ..Limits::default()
+        };
+        lox.set_limits(limits);
+        lox.register_fn("answer", Some(0), |_, _| Ok(Value::Number(42.0)));
+
+        eval("var a = 1; var clock = 2; seed(7);", &mut lox);
+        let expected = Rng::new(7).next_f64();
+
+        meta_command("reset", &mut lox);
+        meta_command("env", &mut lox);
+        assert_eq!(output.take(), "");
+
+        eval("random()", &mut lox);
+        eval("answer()", &mut lox);
+        eval("clock() > 0", &mut lox);
+        assert_eq!(
+            output.contents(),
+            format!("{}\n42\ntrue\n", Value::Number(expected))
+        );
+        assert_eq!(lox.interpreter().limits, limits);
+    }
+
+    #[test]
+    fn load_runs_a_file_in_the_session() {
+        let (mut lox, output) = session();
+        let path = std::env::temp_dir().join(format!("rslox-repl-{}.lox", std::process::id()));
+        std::fs::write(&path, "var loaded = 3;\nprint loaded;\n").unwrap();
+
+        let command = format!("load {}", path.display());
+        assert_eq!(meta_command(&command, &mut lox), None);
+        eval("loaded + 1", &mut lox);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(output.contents(), "3\n4\n");
+    }
+
+    #[test]
+    fn tokens_go_to_the_output() {
+        let (mut lox, output) = session();
+
+        assert_eq!(meta_command("tokens print 1;", &mut lox), None);
+        assert_eq!(
+            output.contents(),
+            "   1:1    Print print \n   1:7    Number 1 1\n   1:8    Semicolon ; \n   1:9    Eof  \n"
+        );
+    }
+
+    #[test]
+    fn help_quit_and_unknown_commands() {
+        let (mut lox, output) = session();
+
+        assert_eq!(meta_command("help", &mut lox), None);
+        assert_eq!(output.take(), format!("{}\n", HELP));
+
+        assert_eq!(meta_command("bogus", &mut lox), None);
+        assert_eq!(
+            output.take(),
+            "Unknown command ':bogus'. Type ':help' for a list.\n"
+        );
+
+        assert_eq!(meta_command("quit", &mut lox), Some(0));
+    }
+
+    #[test]
+    fn complete_input_runs_at_once() {
+        assert!(!is_incomplete("print 1;\n"));
+        assert!(!is_incomplete("1 + 2\n"));
+        assert!(!is_incomplete("{ print 1; }\n"));
+        assert!(!is_incomplete("// a comment {\n"));
+    }
+
+    #[test]
+    fn open_braces_and_parentheses_continue() {
+        assert!(is_incomplete("{\n"));
+        assert!(is_incomplete("if (true) {\n  print 1;\n"));
+        assert!(is_incomplete("print (1 +\n"));
+        assert!(!is_incomplete("if (true) {\n  print 1;\n}\n"));
+    }
+
+    #[test]
+    fn open_strings_and_block_comments_continue() {
+        assert!(is_incomplete("print \"one\n"));
+        assert!(is_incomplete("/* note\n"));
+        assert!(!is_incomplete("print \"one\ntwo\";\n"));
+        assert!(!is_incomplete("/* note\n*/\n"));
+    }
+
+    #[test]
+    fn stray_closing_braces_do_not_wait_for_more() {
+        assert!(!is_incomplete("}\n"));
+        assert!(!is_incomplete(")(\n"));
+    }
+}
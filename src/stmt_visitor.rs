@@ -1,9 +1,10 @@
-use crate::ast::statement::{ExpressionStmt, PrintStmt, VarStmt, BlockStmt, IfStmt};
+use crate::ast::statement::{BlockStmt, ExpressionStmt, IfStmt, PrintStmt, TestStmt, VarStmt};
 
 pub trait StmtVisitor<T> {
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> T;
     fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> T;
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> T;
     fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> T;
+    fn visit_test_stmt(&mut self, stmt: &TestStmt) -> T;
     fn visit_var_stmt(&mut self, stmt: &VarStmt) -> T;
 }
@@ -0,0 +1,238 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::statement::{Stmt, TestStmt},
+    error::RuntimeError,
+    interpreter::Interpreter,
+    value::Value,
+};
+
+pub const SUFFIX: &str = "_test.lox";
+
+/// The test files at `path`: the file itself, or every `*_test.lox` file below
+/// the directory, in sorted order.
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    let mut directories = vec![path.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                directories.push(path);
+            } else if path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(SUFFIX))
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// The name the results report a failure outside the tests under.
+pub const TOP_LEVEL: &str = "top level";
+
+/// Runs the statements outside the tests once in `interpreter`, then the
+/// `test` declarations one by one as the iterator is advanced. Every test
+/// starts from the state the top level left behind: a fresh copy of its
+/// globals, lists and maps included, and of its random generator, with the
+/// regex cache and the limit counters cleared. If the top level fails, that
+/// failure, named `TOP_LEVEL`, is the only result.
+pub fn run<'a>(
+    statements: &'a [Stmt],
+    mut interpreter: Interpreter,
+) -> impl Iterator<Item = (String, Result<(), RuntimeError>)> + 'a {
+    let top_level = statements
+        .iter()
+        .try_for_each(|stmt| interpreter.execute(stmt));
+    let globals = interpreter.environment.deep_copy();
+    let rng = interpreter.rng.clone();
+
+    let tests: Vec<&TestStmt> = match top_level {
+        Ok(()) => statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Test(test) => Some(test),
+                _ => None,
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let failure = top_level
+        .err()
+        .map(|err| (String::from(TOP_LEVEL), Err(err)));
+
+    failure
+        .into_iter()
+        .chain(tests.into_iter().map(move |test| {
+            interpreter.environment = globals.deep_copy();
+            interpreter.rng = rng.clone();
+            interpreter.regexes.clear();
+            interpreter.reset_usage();
+            let result = interpreter.execute(&test.body);
+
+            (name(test), result)
+        }))
+}
+
+fn name(test: &TestStmt) -> String {
+    match &test.name.literal {
+        Some(Value::String(name)) => name.clone(),
+        _ => test.name.lexeme.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::parser::Parser, capture::Capture, error::ErrorKind, lexer::scanner::Scanner};
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let (tokens, _) = Scanner::new(String::from(source)).scan_tokens_with_errors();
+        let (statements, errors) = Parser::new(tokens).parse_with_errors();
+        assert!(errors.is_empty());
+        statements
+    }
+
+    /// Runs the tests in `source`, returning each name with the failure
+    /// message, if any, and what the file printed.
+    fn results(source: &str) -> (Vec<(String, Option<String>)>, String) {
+        let statements = parse(source);
+        let output = Capture::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.output = Box::new(output.clone());
+
+        let results = run(&statements, interpreter)
+            .map(|(name, result)| (name, result.err().map(|err| err.message)))
+            .collect();
+        (results, output.contents())
+    }
+
+    #[test]
+    fn top_level_runs_once() {
+        let (results, output) = results(
+            "print \"setup\";\nvar n = 1;\ntest \"one\" { print n; }\ntest \"two\" { print n + 1; }\nprint \"more setup\";\n",
+        );
+
+        assert_eq!(
+            results,
+            [(String::from("one"), None), (String::from("two"), None)]
+        );
+        assert_eq!(output, "setup\nmore setup\n1\n2\n");
+    }
+
+    #[test]
+    fn tests_do_not_see_each_others_assignments() {
+        let (results, _) = results(
+            "var n = 1;\ntest \"bumps\" { n = n + 1; assertEq(n, 2); }\ntest \"sees the original\" { assertEq(n, 1); }\n",
+        );
+
+        assert_eq!(
+            results,
+            [
+                (String::from("bumps"), None),
+                (String::from("sees the original"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_do_not_see_each_others_list_changes() {
+        let (results, output) = results(
+            "var xs = split(\",\", \"a,b,c,d,e,f\");\nvar same = xs;\nseed(3);\n\
+             test \"shuffles\" { shuffle(same); print xs; }\n\
+             test \"sees the original\" { print xs; assertEq(len(xs), 6); }\n",
+        );
+
+        assert_eq!(
+            results,
+            [
+                (String::from("shuffles"), None),
+                (String::from("sees the original"), None),
+            ]
+        );
+        let printed: Vec<&str> = output.lines().collect();
+        assert_ne!(printed[0], "[a, b, c, d, e, f]");
+        assert_eq!(printed[1], "[a, b, c, d, e, f]");
+    }
+
+    #[test]
+    fn every_test_starts_from_the_same_random_state() {
+        let (_, output) = results(
+            "seed(9);\ntest \"one\" { print random(); }\ntest \"two\" { print random(); }\n",
+        );
+
+        let printed: Vec<&str> = output.lines().collect();
+        assert_eq!(printed.len(), 2);
+        assert_eq!(printed[0], printed[1]);
+    }
+
+    #[test]
+    fn failures_are_reported_per_test() {
+        let (results, _) = results(
+            "test \"fails\" { assertEq(1, 2); }\ntest \"errors\" { print -\"a\"; }\ntest \"passes\" {}\n",
+        );
+
+        let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["fails", "errors", "passes"]);
+        assert!(results[0].1.is_some());
+        assert_eq!(results[1].1.as_deref(), Some("Operand must be a number."));
+        assert_eq!(results[2].1, None);
+    }
+
+    #[test]
+    fn a_failing_top_level_is_the_only_result() {
+        let statements = parse("test \"never\" {}\nexit(3);\n");
+        let results: Vec<_> = run(&statements, Interpreter::new()).collect();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, TOP_LEVEL);
+        assert!(matches!(
+            results[0].1.as_ref().unwrap_err().kind,
+            ErrorKind::Exit(3)
+        ));
+    }
+
+    #[test]
+    fn discover_finds_test_files_in_order() {
+        let root = std::env::temp_dir().join(format!("rslox-discover-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        for file in [
+            "b_test.lox",
+            "a_test.lox",
+            "helper.lox",
+            "nested/c_test.lox",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        let found = discover(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            found,
+            [
+                root.join("a_test.lox"),
+                root.join("b_test.lox"),
+                root.join("nested/c_test.lox"),
+            ]
+        );
+        assert_eq!(
+            discover(Path::new("single.lox")).unwrap(),
+            [PathBuf::from("single.lox")]
+        );
+    }
+}
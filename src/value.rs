@@ -1,9 +1,73 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+use crate::{host::HostFunction, native::NativeFunction};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Nil,
     Bool(bool),
     Number(f64),
     String(String),
+    List(Rc<RefCell<Vec<Value>>>),
+    /// String-keyed values, mostly handed over by the host. Keys are kept sorted.
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    NativeFunction(NativeFunction),
+    HostFunction(Rc<HostFunction>),
+}
+
+impl Value {
+    pub fn list(values: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn map(entries: BTreeMap<String, Value>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    /// A copy that shares no list or map with `self`. `copies` holds the copy
+    /// of every list and map met so far, keyed by address, so that one reached
+    /// twice, even from inside itself, is copied once.
+    pub(crate) fn deep_copy(&self, copies: &mut HashMap<*const (), Value>) -> Value {
+        match self {
+            Value::List(list) => {
+                let key = Rc::as_ptr(list) as *const ();
+                if let Some(copy) = copies.get(&key) {
+                    return copy.clone();
+                }
+
+                let copy = Rc::new(RefCell::new(Vec::new()));
+                copies.insert(key, Value::List(copy.clone()));
+                let values = list
+                    .borrow()
+                    .iter()
+                    .map(|value| value.deep_copy(copies))
+                    .collect();
+                *copy.borrow_mut() = values;
+                Value::List(copy)
+            }
+            Value::Map(map) => {
+                let key = Rc::as_ptr(map) as *const ();
+                if let Some(copy) = copies.get(&key) {
+                    return copy.clone();
+                }
+
+                let copy = Rc::new(RefCell::new(BTreeMap::new()));
+                copies.insert(key, Value::Map(copy.clone()));
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.deep_copy(copies)))
+                    .collect();
+                *copy.borrow_mut() = entries;
+                Value::Map(copy)
+            }
+            value => value.clone(),
+        }
+    }
 }
 
 impl std::fmt::Display for Value {
@@ -13,7 +77,127 @@ impl std::fmt::Display for Value {
             Value::Bool(b) => write!(f, "{}", b),
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, value) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::NativeFunction(function) => write!(f, "<native fn {}>", function.name),
+            Value::HostFunction(function) => write!(f, "<native fn {}>", function.name),
         }
     }
 }
 
+/// Values map to an externally tagged enum with the variants `Nil`, `Bool`,
+/// `Number`, `String`, `List` and `Map`, in that order, so self-describing and
+/// binary formats alike keep their shape. Functions cannot be serialized.
+#[cfg(feature = "serde")]
+mod serde {
+    use std::collections::BTreeMap;
+
+    use serde::{ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Value;
+
+    /// The owned shape of a serialized value; the variant order is part of the format.
+    #[derive(Deserialize)]
+    #[serde(rename = "Value")]
+    enum Serialized {
+        Nil,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        List(Vec<Value>),
+        Map(BTreeMap<String, Value>),
+    }
+
+    impl Serialize for Value {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Value::Nil => serializer.serialize_unit_variant("Value", 0, "Nil"),
+                Value::Bool(b) => serializer.serialize_newtype_variant("Value", 1, "Bool", b),
+                Value::Number(n) => serializer.serialize_newtype_variant("Value", 2, "Number", n),
+                Value::String(s) => serializer.serialize_newtype_variant("Value", 3, "String", s),
+                Value::List(list) => {
+                    serializer.serialize_newtype_variant("Value", 4, "List", &*list.borrow())
+                }
+                Value::Map(map) => {
+                    serializer.serialize_newtype_variant("Value", 5, "Map", &*map.borrow())
+                }
+                Value::NativeFunction(function) => Err(S::Error::custom(format!(
+                    "cannot serialize function '{}'",
+                    function.name
+                ))),
+                Value::HostFunction(function) => Err(S::Error::custom(format!(
+                    "cannot serialize function '{}'",
+                    function.name
+                ))),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Value {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(match Serialized::deserialize(deserializer)? {
+                Serialized::Nil => Value::Nil,
+                Serialized::Bool(b) => Value::Bool(b),
+                Serialized::Number(n) => Value::Number(n),
+                Serialized::String(s) => Value::String(s),
+                Serialized::List(list) => Value::list(list),
+                Serialized::Map(map) => Value::map(map),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::collections::BTreeMap;
+
+        use crate::{lox::Lox, value::Value};
+
+        fn sample() -> Value {
+            Value::map(BTreeMap::from([(
+                String::from("xs"),
+                Value::list(vec![Value::Nil, Value::Bool(true), Value::Number(1.5)]),
+            )]))
+        }
+
+        #[test]
+        fn json_is_externally_tagged() {
+            let json = serde_json::to_string(&sample()).unwrap();
+            assert_eq!(
+                json,
+                r#"{"Map":{"xs":{"List":["Nil",{"Bool":true},{"Number":1.5}]}}}"#
+            );
+            assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), sample());
+        }
+
+        #[test]
+        fn bincode_round_trips() {
+            let bytes = bincode::serialize(&sample()).unwrap();
+            assert_eq!(bincode::deserialize::<Value>(&bytes).unwrap(), sample());
+        }
+
+        #[test]
+        fn functions_fail_to_serialize() {
+            let mut lox = Lox::new();
+            let err = serde_json::to_string(&lox.eval("clock").unwrap()).unwrap_err();
+            assert_eq!(err.to_string(), "cannot serialize function 'clock'");
+        }
+    }
+}
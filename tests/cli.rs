@@ -0,0 +1,190 @@
+//! Runs the `rslox` binary and checks what it writes to stdout and stderr.
+
+use std::{path::PathBuf, process::Command};
+
+/// A script written to a fresh temporary file, removed when dropped.
+struct Script(PathBuf);
+
+impl Script {
+    fn new(name: &str, source: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("rslox-cli-{}-{}", std::process::id(), name));
+        std::fs::write(&path, source).unwrap();
+        Self(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for Script {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Runs `rslox` with `args`, returning the exit status, stdout and stderr.
+fn rslox(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rslox"))
+        .args(args)
+        .output()
+        .unwrap();
+
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn print_goes_to_stdout_and_errors_to_stderr() {
+    let script = Script::new("errors.lox", "print \"before\";\nprint nil + 1;\n");
+
+    let (status, stdout, stderr) = rslox(&[script.path()]);
+    assert_eq!(status, 70);
+    assert_eq!(stdout, "before\n");
+    assert_eq!(
+        stderr,
+        "[line 2] Error at '+': Operands must be two numbers or two strings.\n"
+    );
+}
+
+#[test]
+fn a_missing_file_is_reported_on_stderr() {
+    let (status, stdout, stderr) = rslox(&["/nonexistent/script.lox"]);
+    assert_eq!(status, 1);
+    assert_eq!(stdout, "");
+    assert!(stderr.starts_with("Failed to read file: "), "{}", stderr);
+}
+
+#[test]
+fn invalid_ast_is_reported_on_stderr() {
+    let ast = Script::new("ast.json", "{ \"version\": 2 }");
+
+    let (status, stdout, stderr) = rslox(&["--run-json", ast.path()]);
+    assert_ne!(status, 0);
+    assert_eq!(stdout, "");
+    assert!(stderr.starts_with("Invalid AST: "), "{}", stderr);
+}
+
+#[test]
+fn an_unwritable_profile_is_reported_on_stderr() {
+    let script = Script::new("profiled.lox", "print 1;\n");
+
+    let (status, stdout, stderr) = rslox(&[
+        "--profile",
+        "--collapsed",
+        "/nonexistent/dir/stacks.txt",
+        script.path(),
+    ]);
+    assert_ne!(status, 0);
+    assert_eq!(stdout, "1\n");
+    assert!(stderr.contains("Failed to write file: "), "{}", stderr);
+}
+
+#[test]
+fn usage_goes_to_stderr() {
+    let (status, stdout, stderr) = rslox(&["--tokens"]);
+    assert_eq!(status, 64);
+    assert_eq!(stdout, "");
+    assert!(stderr.starts_with("Usage: rslox"), "{}", stderr);
+}
+
+#[test]
+fn test_coverage_counts_the_top_level_once() {
+    let tests = Script::new(
+        "covered_test.lox",
+        "var x = 1;\ntest \"one\" {\n  assertEq(x, 1);\n}\ntest \"two\" {\n  assertTrue(true);\n}\n",
+    );
+    let info = Script::new("covered.info", "");
+
+    let (status, _, stderr) = rslox(&["test", "--coverage", info.path(), tests.path()]);
+    assert_eq!(status, 0, "{}", stderr);
+
+    let lcov = std::fs::read_to_string(&info.0).unwrap();
+    let lines: Vec<&str> = lcov
+        .lines()
+        .filter(|line| line.starts_with("DA:"))
+        .collect();
+    // The line of a `test` holds both its declaration and the body's block.
+    assert_eq!(lines, ["DA:1,1", "DA:2,2", "DA:3,1", "DA:5,2", "DA:6,1"]);
+}
+
+const TRACED: &str = "var a = 1;\n{\n  var b = a + 2;\n  print b * 3;\n}\nprint \"done\";\n";
+
+#[test]
+fn trace_indents_by_block_and_logs_values() {
+    let script = Script::new("traced.lox", TRACED);
+
+    let (status, stdout, stderr) = rslox(&["--trace", script.path()]);
+    assert_eq!(status, 0);
+    assert_eq!(stdout, "9\ndone\n");
+    assert_eq!(
+        stderr,
+        "\
+[line 1] (var a 1)
+[line 1]   1 => 1
+[line 2] (block)
+[line 3]   (var b (+ a 2))
+[line 3]     a => 1
+[line 3]     2 => 2
+[line 3]     (+ a 2) => 3
+[line 4]   (print (* b 3))
+[line 4]     b => 3
+[line 4]     3 => 3
+[line 4]     (* b 3) => 9
+[line 6] (print \"done\")
+[line 6]   \"done\" => \"done\"
+"
+    );
+}
+
+#[test]
+fn trace_lines_keeps_only_the_range() {
+    let script = Script::new("ranged.lox", TRACED);
+
+    let (status, stdout, stderr) = rslox(&["--trace", "--lines", "3-4", script.path()]);
+    assert_eq!(status, 0);
+    assert_eq!(stdout, "9\ndone\n");
+    assert_eq!(
+        stderr,
+        "\
+[line 3]   (var b (+ a 2))
+[line 3]     a => 1
+[line 3]     2 => 2
+[line 3]     (+ a 2) => 3
+[line 4]   (print (* b 3))
+[line 4]     b => 3
+[line 4]     3 => 3
+[line 4]     (* b 3) => 9
+"
+    );
+
+    let (_, _, stderr) = rslox(&["--trace", "--lines", "5-", script.path()]);
+    assert_eq!(
+        stderr,
+        "[line 6] (print \"done\")\n[line 6]   \"done\" => \"done\"\n"
+    );
+
+    let (_, _, stderr) = rslox(&["--trace", "--lines", "-2", script.path()]);
+    assert_eq!(
+        stderr,
+        "[line 1] (var a 1)\n[line 1]   1 => 1\n[line 2] (block)\n"
+    );
+
+    let (_, _, stderr) = rslox(&["--trace", "--lines", "1", script.path()]);
+    assert_eq!(stderr, "[line 1] (var a 1)\n[line 1]   1 => 1\n");
+}
+
+#[test]
+fn a_malformed_trace_range_is_a_usage_error() {
+    let script = Script::new("malformed.lox", TRACED);
+
+    for range in ["4-x", "three", "5-4"] {
+        let (status, stdout, stderr) = rslox(&["--trace", "--lines", range, script.path()]);
+        assert_eq!(status, 64, "{}", range);
+        assert_eq!(stdout, "");
+        assert!(stderr.starts_with("Usage: rslox"), "{}", stderr);
+    }
+}
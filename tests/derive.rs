@@ -0,0 +1,120 @@
+// `Borrow` in scope used to make the derived `map.borrow()` ambiguous.
+#[allow(unused_imports)]
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+use rslox::{ConversionError, FromLox, IntoLox, Lox, Value};
+
+#[derive(Debug, Clone, PartialEq, FromLox, IntoLox)]
+struct Point {
+    x: f64,
+    y: f64,
+    label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromLox, IntoLox)]
+struct Path {
+    name: String,
+    points: Vec<Point>,
+}
+
+fn point(x: f64, y: f64, label: Option<&str>) -> Point {
+    Point {
+        x,
+        y,
+        label: label.map(String::from),
+    }
+}
+
+#[test]
+fn structs_become_maps_keyed_by_field() {
+    let value = point(1.0, 2.0, Some("a")).into_lox();
+
+    let expected = Value::map(BTreeMap::from([
+        (String::from("label"), Value::String(String::from("a"))),
+        (String::from("x"), Value::Number(1.0)),
+        (String::from("y"), Value::Number(2.0)),
+    ]));
+    assert_eq!(value.to_string(), expected.to_string());
+}
+
+#[test]
+fn round_trip() {
+    let path = Path {
+        name: String::from("zigzag"),
+        points: vec![point(0.0, 0.0, None), point(1.0, -1.0, Some("turn"))],
+    };
+
+    let value = path.clone().into_lox();
+    assert_eq!(Path::from_lox(&value), Ok(path));
+}
+
+#[test]
+fn round_trip_through_a_script() {
+    let mut lox = Lox::new();
+    lox.register_typed("shift", |_, (mut point, dx): (Point, f64)| {
+        point.x += dx;
+        Ok(point)
+    });
+    lox.set_global("p", point(1.0, 2.0, Some("start")));
+
+    let value = lox.eval("shift(p, 10)").unwrap();
+    assert_eq!(Point::from_lox(&value), Ok(point(11.0, 2.0, Some("start"))));
+}
+
+#[test]
+fn a_missing_field_reads_as_nil() {
+    let value = Value::map(BTreeMap::from([
+        (String::from("x"), Value::Number(1.0)),
+        (String::from("y"), Value::Number(2.0)),
+    ]));
+    assert_eq!(Point::from_lox(&value), Ok(point(1.0, 2.0, None)));
+
+    let value = Value::map(BTreeMap::from([(String::from("x"), Value::Number(1.0))]));
+    assert_eq!(
+        Point::from_lox(&value),
+        Err(ConversionError::new("a map whose 'y' is a number"))
+    );
+}
+
+#[test]
+fn a_wrong_type_is_an_error() {
+    let value = Value::map(BTreeMap::from([
+        (String::from("x"), Value::Number(1.0)),
+        (String::from("y"), Value::String(String::from("two"))),
+    ]));
+    assert_eq!(
+        Point::from_lox(&value),
+        Err(ConversionError::new("a map whose 'y' is a number"))
+    );
+
+    assert_eq!(
+        Point::from_lox(&Value::Number(1.0)),
+        Err(ConversionError::new("a map"))
+    );
+}
+
+#[test]
+fn a_wrong_argument_is_reported_at_the_call() {
+    let mut lox = Lox::new();
+    lox.register_typed("norm", |_, (point,): (Point,)| {
+        Ok((point.x * point.x + point.y * point.y).sqrt())
+    });
+
+    let err = lox.eval("norm(3)").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "[line 1] Error at ')': Argument 1 to 'norm' must be a map."
+    );
+}
+
+#[test]
+fn scripts_read_derived_maps() {
+    let mut lox = Lox::new();
+    lox.set_global("p", point(1.0, 2.0, Some("start")));
+
+    assert_eq!(lox.eval("get(p, \"x\")").unwrap(), Value::Number(1.0));
+    assert_eq!(lox.eval("get(p, \"z\")").unwrap(), Value::Nil);
+    assert_eq!(lox.eval("len(p)").unwrap(), Value::Number(3.0));
+    assert_eq!(lox.eval("keys(p)").unwrap().to_string(), "[label, x, y]");
+}